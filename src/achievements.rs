@@ -0,0 +1,240 @@
+use image::RgbImage;
+
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::BTreeMap,
+    fs::{
+        read_to_string,
+        write,
+    },
+};
+
+use crate::{
+    difficulty::Difficulty,
+    font,
+    score,
+    world::{
+        Outcome,
+        World,
+        WorldEvent,
+    },
+    EnemyType,
+};
+
+pub const ACHIEVEMENT_PATH: &str = "data/achievements.json";
+// what's been unlocked and the lifetime counters, kept between sessions
+pub const PROGRESS_PATH: &str = "achievements.json";
+
+// the shipped achievements with their names, descriptions and unlock conditions, baked in for
+// when data/achievements.json won't load
+const BUILTIN_ACHIEVEMENTS: &str = include_str!("../data/achievements.json");
+
+// seconds an unlock stays on screen
+pub const TOAST_TIME: f64 = 3.0;
+const TOAST_SCALE: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    // kills over every session, of one enemy type or of any
+    Kills {
+        #[serde(default)]
+        enemy: Option<EnemyType>,
+        count: u64,
+    },
+    // clear wave `wave` (or any later one) without any player losing HP during it
+    FlawlessWave { wave: usize },
+    // win a level, on one difficulty if it's given, and without ever taking the Nova if
+    // `basic_attack_only` is set
+    Win {
+        #[serde(default)]
+        difficulty: Option<Difficulty>,
+        #[serde(default)]
+        basic_attack_only: bool,
+    },
+    // a score of at least `points` in one run
+    Score { points: u64 },
+    // reach experience level `level` in one run
+    Level { level: u32 },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Achievement {
+    // the key progress is saved under, so names can be reworded without losing unlocks
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub condition: Condition,
+}
+
+#[derive(Deserialize)]
+struct AchievementFile {
+    achievements: Vec<Achievement>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Progress {
+    // achievement id to the date it was unlocked, YYYY-MM-DD
+    #[serde(default)]
+    pub unlocked: BTreeMap<String, String>,
+    // kills by archetype name
+    #[serde(default)]
+    pub kills: BTreeMap<String, u64>,
+}
+
+pub struct Achievements {
+    pub list: Vec<Achievement>,
+    pub progress: Progress,
+    // whether any player has lost HP since the current wave started
+    hurt_this_wave: bool,
+    // both players' HP after the last tick, to spot it going down
+    last_hp: f64,
+}
+
+impl Achievements {
+    // Reads the definitions from `ACHIEVEMENT_PATH`, falling back to the built-in ones, and the
+    // progress so far from `PROGRESS_PATH`, if there is any
+    pub fn load() -> Self {
+        let loaded = read_to_string(ACHIEVEMENT_PATH)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str::<AchievementFile>(&json).map_err(|e| e.to_string()));
+        let list = match loaded {
+            Ok(file) => file.achievements,
+            Err(e) => {
+                println!("Warning: couldn't load {ACHIEVEMENT_PATH} ({e}), using built-in achievements");
+                serde_json::from_str::<AchievementFile>(BUILTIN_ACHIEVEMENTS).unwrap().achievements
+            },
+        };
+
+        let progress = read_to_string(PROGRESS_PATH)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+
+        Achievements { list, progress, hurt_this_wave: false, last_hp: 0.0 }
+    }
+    pub fn save(&self) {
+        match serde_json::to_string_pretty(&self.progress) {
+            Ok(json) => {
+                if let Err(e) = write(PROGRESS_PATH, json) {
+                    println!("Warning: couldn't save {PROGRESS_PATH} ({e})");
+                }
+            },
+            Err(e) => println!("Warning: couldn't serialize achievements ({e})"),
+        }
+    }
+    pub fn total_kills(&self) -> u64 {
+        self.progress.kills.values().sum()
+    }
+    // Starts watching a new world, for when one replaces the last so its HP isn't compared with
+    // how the old run ended
+    pub fn reset(&mut self, world: &World) {
+        self.last_hp = total_hp(world);
+        self.hurt_this_wave = false;
+    }
+    // Notes whether anyone lost HP, once per tick after the world has moved on and before that
+    // tick's events are observed. Damage over time and the storm count, not just hits.
+    pub fn watch(&mut self, world: &World) {
+        let hp = total_hp(world);
+        if hp < self.last_hp {
+            self.hurt_this_wave = true;
+        }
+        self.last_hp = hp;
+    }
+    // Updates the counters from one world event and unlocks whatever it completes. Returns the
+    // names of the newly unlocked achievements.
+    pub fn observe(&mut self, event: &WorldEvent, world: &World) -> Vec<String> {
+        match event {
+            WorldEvent::Killed(enemy_type) => {
+                *self.progress.kills.entry(enemy_type.archetype_name().to_string()).or_insert(0) += 1;
+            },
+            WorldEvent::WaveStarted(_) => self.hurt_this_wave = false,
+            _ => {},
+        }
+
+        let mut unlocked = Vec::new();
+        for achievement in self.list.iter() {
+            if self.progress.unlocked.contains_key(&achievement.id) {
+                continue;
+            }
+            if self.met(&achievement.condition, event, world) {
+                println!("Achievement unlocked: {} ({})", achievement.name, achievement.description);
+                unlocked.push(achievement.clone());
+            }
+        }
+        for achievement in unlocked.iter() {
+            self.progress.unlocked.insert(achievement.id.clone(), score::today());
+        }
+
+        // kills pile up fast, so the counters are only written out with an unlock or at the end
+        // of a level here, and by main when a level is left partway
+        if !unlocked.is_empty() || matches!(event, WorldEvent::Finished(_)) {
+            self.save();
+        }
+        unlocked.into_iter().map(|achievement| achievement.name).collect()
+    }
+    fn met(&self, condition: &Condition, event: &WorldEvent, world: &World) -> bool {
+        match (condition, event) {
+            (Condition::Kills { enemy: Some(enemy), count }, WorldEvent::Killed(_)) => {
+                self.progress.kills.get(enemy.archetype_name()).copied().unwrap_or(0) >= *count
+            },
+            (Condition::Kills { enemy: None, count }, WorldEvent::Killed(_)) => {
+                self.total_kills() >= *count
+            },
+            (Condition::FlawlessWave { wave }, WorldEvent::WaveCleared(cleared)) => {
+                cleared >= wave && !self.hurt_this_wave
+            },
+            (Condition::Win { difficulty, basic_attack_only }, WorldEvent::Finished(Outcome::Won)) => {
+                let nova = std::iter::once(&world.player)
+                    .chain(world.partner.iter())
+                    .any(|player| player.secondary.is_some());
+                difficulty.is_none_or(|difficulty| difficulty == world.difficulty)
+                    && !(*basic_attack_only && nova)
+            },
+            (Condition::Score { points }, WorldEvent::Killed(_) | WorldEvent::Finished(_)) => {
+                world.score.total >= *points
+            },
+            (Condition::Level { level }, WorldEvent::LevelUp(_)) => {
+                world.experience.level >= *level
+            },
+            _ => false,
+        }
+    }
+}
+
+// both players' HP, not counting anything below zero
+fn total_hp(world: &World) -> f64 {
+    std::iter::once(&world.player)
+        .chain(world.partner.iter())
+        .map(|player| player.hp.max(0.0))
+        .sum()
+}
+
+// A banner across the top of the frame for a freshly unlocked achievement
+pub fn draw_toast(image: &mut RgbImage, name: &str) {
+    let (width, _) = image.dimensions();
+    let line_height = font::GLYPH_HEIGHT * TOAST_SCALE;
+    let margin = 8;
+    let banner_height = margin * 3 + line_height * 2;
+
+    for y in 0..banner_height.min(image.height()) {
+        for x in 0..width {
+            let border = y < 2 || y + 2 >= banner_height;
+            let color = if border { [230,180,30] } else { [30,30,40] };
+            image.put_pixel(x, y, color.into());
+        }
+    }
+
+    // cut the name short rather than let it run off the edge, and skip the text if nothing fits
+    let fits = (width.saturating_sub(margin * 2) / ((font::GLYPH_WIDTH + 1) * TOAST_SCALE)) as usize;
+    if fits == 0 {
+        return;
+    }
+    let name: String = name.chars().take(fits).collect();
+
+    let title = "ACHIEVEMENT UNLOCKED";
+    let centred = |text: &str| width.saturating_sub(font::text_width(text, TOAST_SCALE)) / 2;
+    font::draw_text(image, title, centred(title), margin, TOAST_SCALE, [230,180,30]);
+    font::draw_text(image, &name, centred(&name), margin * 2 + line_height, TOAST_SCALE, [255,255,255]);
+}
@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::HashMap,
+    fs::read_to_string,
+};
+
+use crate::{
+    boss::{
+        Pattern,
+        Phase,
+    },
+    damage::DamageType,
+    difficulty::Difficulty,
+    status::{
+        StatusEffect,
+        StatusKind,
+    },
+    Action,
+    EnemyType,
+};
+
+pub const ARCHETYPE_PATH: &str = "data/archetypes.json";
+
+// the stock enemy and player stats as shipped, baked in for when data/archetypes.json won't load
+const BUILTIN_ARCHETYPES: &str = include_str!("../data/archetypes.json");
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Behaviour {
+    // driven by input rather than AI
+    Player,
+    // walk straight at the player
+    Chase,
+    // close to firing range, then hold that distance
+    Kite,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Archetype {
+    pub name: String,
+    pub max_hp: f64,
+    pub size: f64,
+    // pixels per second; the top speed for the player
+    pub speed: f64,
+    // pixels per second squared, only used by the player so far
+    #[serde(default)]
+    pub acceleration: f64,
+    #[serde(default)]
+    pub friction: f64,
+    // damage per second of touching the player
+    pub contact_dps: f64,
+    // flat damage taken off every hit
+    #[serde(default)]
+    pub armour: f64,
+    // damage multipliers by type, 1 for anything not listed
+    #[serde(default)]
+    pub resistances: HashMap<DamageType, f64>,
+    // what `action` deals
+    #[serde(default)]
+    pub damage_type: DamageType,
+    // chance from 0 to 1 of a hit landing as a critical
+    #[serde(default)]
+    pub crit_chance: f64,
+    // seconds of immunity after taking a hit
+    #[serde(default)]
+    pub invulnerability: f64,
+    pub color: [u8; 3],
+    pub action: Action,
+    // seconds between uses of `action`
+    pub cooldown: f64,
+    pub fire_range: f64,
+    pub behaviour: Behaviour,
+    // points for killing one, before the combo multiplier
+    #[serde(default)]
+    pub score: u64,
+    // experience the player gets for killing one
+    #[serde(default)]
+    pub xp: f64,
+    // status effects its action inflicts when it lands
+    #[serde(default)]
+    pub on_hit: Vec<StatusEffect>,
+    // attack patterns by HP threshold, which makes this a boss
+    #[serde(default)]
+    pub phases: Vec<Phase>,
+}
+
+#[derive(Deserialize)]
+struct ArchetypeFile {
+    archetypes: Vec<Archetype>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Archetypes {
+    by_name: HashMap<String, Archetype>,
+}
+
+impl Archetypes {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let file: ArchetypeFile = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let by_name: HashMap<String, Archetype> = file.archetypes
+            .into_iter()
+            .map(|archetype| (archetype.name.clone(), archetype))
+            .collect();
+
+        for required in ["player", "weak", "medium", "strong", "boss"] {
+            if !by_name.contains_key(required) {
+                return Err(format!("missing archetype \"{required}\""));
+            }
+        }
+
+        Ok(Archetypes { by_name })
+    }
+    // Reads `ARCHETYPE_PATH`, falling back to the copy compiled into the binary if it can't be
+    // read or parsed.
+    pub fn load() -> Self {
+        let loaded = read_to_string(ARCHETYPE_PATH)
+            .map_err(|e| e.to_string())
+            .and_then(|json| Archetypes::from_json(&json));
+
+        match loaded {
+            Ok(archetypes) => archetypes,
+            Err(e) => {
+                println!("Warning: couldn't load {ARCHETYPE_PATH} ({e}), using built-in archetypes");
+                Archetypes::from_json(BUILTIN_ARCHETYPES).unwrap()
+            },
+        }
+    }
+    // A copy with every enemy scaled by the difficulty's profile. The player is left alone.
+    pub fn with_difficulty(&self, difficulty: Difficulty) -> Self {
+        let profile = difficulty.profile();
+        let mut scaled = self.clone();
+
+        for (name, archetype) in scaled.by_name.iter_mut() {
+            if name == "player" {
+                continue;
+            }
+            archetype.max_hp *= profile.hp;
+            archetype.speed *= profile.speed;
+            archetype.contact_dps *= profile.damage;
+            archetype.action.scale(profile.damage);
+            for effect in archetype.on_hit.iter_mut() {
+                if matches!(effect.kind, StatusKind::Poison | StatusKind::Burn) {
+                    effect.magnitude *= profile.damage;
+                }
+            }
+            for pattern in archetype.phases.iter_mut().flat_map(|phase| phase.patterns.iter_mut()) {
+                match pattern {
+                    Pattern::Charge { speed, .. } => *speed *= profile.speed,
+                    Pattern::RadialBurst { damage, .. } => *damage *= profile.damage,
+                    Pattern::Summon { .. } => {},
+                }
+            }
+        }
+
+        scaled
+    }
+    pub fn player(&self) -> &Archetype {
+        &self.by_name["player"]
+    }
+    pub fn enemy(&self, enemy_type: &EnemyType) -> &Archetype {
+        &self.by_name[enemy_type.archetype_name()]
+    }
+}
@@ -0,0 +1,284 @@
+// Seeded arena generator, as an alternative to hand-made levels. The same seed always gives the
+// same arena, and the result is an ordinary `Level`, so it can be written to `levels/` and shared
+// like any other.
+//
+// The arena is laid out on a grid: cellular automata grow cave-like clumps of wall, a ring around
+// the edge and a patch around the player start are kept open, and any open pocket that can't be
+// reached from the start is filled in. That leaves every open cell, and so every spawn point,
+// connected to the player.
+
+use rand::Rng;
+
+use std::{
+    collections::VecDeque,
+    fs::write,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use crate::{
+    args,
+    boundary::Boundary,
+    level::{
+        Hazard,
+        Level,
+        Rect,
+        Spawn,
+        WaveSettings,
+        WinCondition,
+        LEVEL_DIR,
+    },
+    rng::GameRng,
+    EnemyType,
+    WIDTH,
+    HEIGHT,
+};
+
+// pixels per grid cell, comfortably wider than anything the waves send
+pub const CELL: f64 = 25.0;
+// chance an interior cell starts out as wall
+const FILL: f64 = 0.52;
+const SMOOTHING_STEPS: usize = 4;
+// cells kept open around the edge, where waves come in
+const BORDER: usize = 2;
+// cells within this many pixels of the player start are kept open
+const START_CLEARING: f64 = 60.0;
+// scripted spawns and hazards stay at least this far from the start, in pixels
+const SPAWN_DISTANCE: f64 = 150.0;
+const HAZARD_DISTANCE: f64 = 75.0;
+const SPAWN_POINTS: usize = 3;
+const HAZARDS: usize = 4;
+const HAZARD_DPS: f64 = 3.0;
+const WAVES: usize = 5;
+
+struct Grid {
+    cols: usize,
+    rows: usize,
+    // row major, true for wall
+    walls: Vec<bool>,
+}
+
+impl Grid {
+    fn wall(&self, col: usize, row: usize) -> bool {
+        self.walls[row * self.cols + col]
+    }
+    fn set(&mut self, col: usize, row: usize, wall: bool) {
+        self.walls[row * self.cols + col] = wall;
+    }
+    fn centre(col: usize, row: usize) -> (f64, f64) {
+        ((col as f64 + 0.5) * CELL, (row as f64 + 0.5) * CELL)
+    }
+    fn on_border(&self, col: usize, row: usize) -> bool {
+        col < BORDER || row < BORDER || col >= self.cols - BORDER || row >= self.rows - BORDER
+    }
+    // walls among the eight cells around this one
+    fn wall_neighbours(&self, col: usize, row: usize) -> usize {
+        let mut count = 0;
+        for dr in -1i64..=1 {
+            for dc in -1i64..=1 {
+                if dr == 0 && dc == 0 {
+                    continue;
+                }
+                let (c, r) = (col as i64 + dc, row as i64 + dr);
+                if c >= 0 && r >= 0 && (c as usize) < self.cols && (r as usize) < self.rows && self.wall(c as usize, r as usize) {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+    // Every open cell reachable from `from` moving up, down, left and right
+    fn reachable(&self, from: (usize, usize)) -> Vec<bool> {
+        let mut seen = vec![false; self.walls.len()];
+        let mut queue = VecDeque::from([from]);
+        seen[from.1 * self.cols + from.0] = true;
+
+        while let Some((col, row)) = queue.pop_front() {
+            let neighbours = [
+                (col.wrapping_sub(1), row),
+                (col + 1, row),
+                (col, row.wrapping_sub(1)),
+                (col, row + 1),
+            ];
+            for (c, r) in neighbours {
+                if c >= self.cols || r >= self.rows {
+                    continue;
+                }
+                let idx = r * self.cols + c;
+                if !seen[idx] && !self.walls[idx] {
+                    seen[idx] = true;
+                    queue.push_back((c, r));
+                }
+            }
+        }
+        seen
+    }
+    // Covers the walls with as few rectangles as a greedy sweep finds: each one grows right as
+    // far as it can, then down as far as its whole width allows
+    fn obstacles(&self) -> Vec<Rect> {
+        let mut covered = vec![false; self.walls.len()];
+        let mut rects = Vec::new();
+
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                if !self.wall(col, row) || covered[row * self.cols + col] {
+                    continue;
+                }
+                let free = |c: usize, r: usize| self.wall(c, r) && !covered[r * self.cols + c];
+
+                let mut end_col = col + 1;
+                while end_col < self.cols && free(end_col, row) {
+                    end_col += 1;
+                }
+                let mut end_row = row + 1;
+                while end_row < self.rows && (col..end_col).all(|c| free(c, end_row)) {
+                    end_row += 1;
+                }
+
+                for r in row..end_row {
+                    for c in col..end_col {
+                        covered[r * self.cols + c] = true;
+                    }
+                }
+                rects.push(Rect {
+                    x: col as f64 * CELL,
+                    y: row as f64 * CELL,
+                    w: (end_col - col) as f64 * CELL,
+                    h: (end_row - row) as f64 * CELL,
+                });
+            }
+        }
+        rects
+    }
+}
+
+pub fn generate(seed: u64) -> Level {
+    let mut rng = GameRng::new(seed);
+    let (cols, rows) = ((WIDTH as f64 / CELL) as usize, (HEIGHT as f64 / CELL) as usize);
+    let start = (WIDTH as f64 / 2.0, HEIGHT as f64 / 2.0);
+    let near_start = |col: usize, row: usize| {
+        let (x, y) = Grid::centre(col, row);
+        (x - start.0).hypot(y - start.1) <= START_CLEARING
+    };
+
+    let mut grid = Grid { cols, rows, walls: vec![false; cols * rows] };
+    for row in 0..rows {
+        for col in 0..cols {
+            let open = grid.on_border(col, row) || near_start(col, row);
+            grid.set(col, row, !open && rng.gen_bool(FILL));
+        }
+    }
+
+    // a cell turns to wall with five or more wall neighbours and stays one with four, which
+    // clumps the noise into caves
+    for _ in 0..SMOOTHING_STEPS {
+        let mut next = Grid { cols, rows, walls: grid.walls.clone() };
+        for row in 0..rows {
+            for col in 0..cols {
+                let neighbours = grid.wall_neighbours(col, row);
+                let wall = neighbours >= 5 || (grid.wall(col, row) && neighbours >= 4);
+                let open = grid.on_border(col, row) || near_start(col, row);
+                next.set(col, row, wall && !open);
+            }
+        }
+        grid = next;
+    }
+
+    let start_cell = ((start.0 / CELL) as usize, (start.1 / CELL) as usize);
+    // the open ring around the edge is one connected piece, so reaching any of it reaches all of
+    // it. If the caves wall the start in, a corridor is cut straight up to the ring.
+    if !grid.reachable(start_cell)[0] {
+        for row in 0..start_cell.1 {
+            grid.set(start_cell.0, row, false);
+        }
+    }
+    let reachable = grid.reachable(start_cell);
+    for (idx, seen) in reachable.iter().enumerate() {
+        if !seen {
+            grid.walls[idx] = true;
+        }
+    }
+
+    // open cells at least `distance` from the start, shuffled by the seed
+    let open_cells = |distance: f64, rng: &mut GameRng| {
+        let mut cells: Vec<(f64, f64)> = (0..rows)
+            .flat_map(|row| (0..cols).map(move |col| (col, row)))
+            .filter(|&(col, row)| !grid.wall(col, row))
+            .map(|(col, row)| Grid::centre(col, row))
+            .filter(|(x, y)| (x - start.0).hypot(y - start.1) >= distance)
+            .collect();
+        for i in (1..cells.len()).rev() {
+            cells.swap(i, rng.gen_range(0..=i));
+        }
+        cells
+    };
+
+    let spawns: Vec<Spawn> = open_cells(SPAWN_DISTANCE, &mut rng)
+        .into_iter()
+        .take(SPAWN_POINTS)
+        .enumerate()
+        .map(|(i, (x, y))| Spawn {
+            time: 4.0 + i as f64 * 10.0,
+            enemy: [EnemyType::Weak, EnemyType::Medium, EnemyType::Strong][i % 3].clone(),
+            x,
+            y,
+            count: 2,
+        })
+        .collect();
+
+    let hazards: Vec<Hazard> = open_cells(HAZARD_DISTANCE, &mut rng)
+        .into_iter()
+        .filter(|(x, y)| !spawns.iter().any(|spawn| spawn.x == *x && spawn.y == *y))
+        .take(HAZARDS)
+        .map(|(x, y)| Hazard {
+            area: Rect { x: x - CELL / 2.0, y: y - CELL / 2.0, w: CELL, h: CELL },
+            dps: HAZARD_DPS,
+        })
+        .collect();
+
+    Level {
+        name: format!("Generated {seed}"),
+        width: WIDTH,
+        height: HEIGHT,
+        background: [255,255,255],
+        player_start: Some([start.0, start.1]),
+        boundary: Boundary::Clamp,
+        obstacles: grid.obstacles(),
+        exit: None,
+        hazards,
+        spawns,
+        waves: Some(WaveSettings { count: Some(WAVES) }),
+        win: Some(WinCondition::KillAll),
+        time_limit: None,
+    }
+}
+
+// Where a generated arena is written so it shows up with the other levels
+pub fn path(seed: u64) -> PathBuf {
+    Path::new(LEVEL_DIR).join(format!("generated_{seed}.json"))
+}
+
+// Reads `--arena [seed]` from the command line: generates that arena (from a random seed if none
+// is given) and writes it to `levels/` for sharing. None if the flag isn't there.
+pub fn from_args() -> Option<Level> {
+    if !args::present(&["--arena"]) {
+        return None;
+    }
+    let seed = match args::value(&["--arena"]) {
+        Some(seed) => seed.parse().unwrap_or_else(|_| {
+            println!("Warning: arena seed \"{seed}\" isn't a number, picking one");
+            rand::random()
+        }),
+        None => rand::random(),
+    };
+
+    let level = generate(seed);
+    let path = path(seed);
+    match level.to_json().and_then(|json| write(&path, json).map_err(|e| e.to_string())) {
+        Ok(()) => println!("Generated arena {seed}, saved to {}", path.display()),
+        Err(e) => println!("Warning: couldn't save arena {seed} to {} ({e})", path.display()),
+    }
+    Some(level)
+}
@@ -0,0 +1,26 @@
+// Command line flags. Each takes its value either as the next argument or after an `=`.
+
+// The value given for any of `names`, the last one winning if it's repeated
+pub fn value(names: &[&str]) -> Option<String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let mut found = None;
+    for (i, arg) in args.iter().enumerate() {
+        for name in names {
+            if let Some(value) = arg.strip_prefix(&format!("{name}=")) {
+                found = Some(value.to_string());
+            } else if arg == name {
+                // a flag followed by another flag has no value
+                found = args.get(i + 1).filter(|next| !next.starts_with('-')).cloned();
+            }
+        }
+    }
+    found
+}
+
+// Whether any of `names` was given, with or without a value
+pub fn present(names: &[&str]) -> bool {
+    std::env::args().skip(1).any(|arg| {
+        names.iter().any(|name| arg == *name || arg.starts_with(&format!("{name}=")))
+    })
+}
@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+
+use std::f64::consts::TAU;
+
+use crate::{
+    damage::Damage,
+    level::Rect,
+    projectile::Projectile,
+    EnemyType,
+    Object,
+};
+
+// seconds the boss stands still and flashes before a charge
+pub const CHARGE_WINDUP: f64 = 0.6;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pattern {
+    // wind up, then rush at where the player was
+    Charge { speed: f64, duration: f64 },
+    // a ring of `count` projectiles in every direction
+    RadialBurst { count: usize, damage: f64 },
+    // bring in minions next to the boss
+    Summon { enemy: EnemyType, count: usize },
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Phase {
+    // the phase starts once hp / max_hp drops to this
+    pub below: f64,
+    // shown when the phase starts
+    #[serde(default)]
+    pub announce: Option<String>,
+    // seconds between patterns
+    pub interval: f64,
+    // cycled through in order
+    pub patterns: Vec<Pattern>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BossState {
+    pub phases: Vec<Phase>,
+    pub phase: usize,
+    pub pattern: usize,
+    pub timer: f64,
+    pub windup: Option<f64>,
+    // velocity and seconds left
+    pub charge: Option<((f64, f64), f64)>,
+}
+
+pub enum BossEffect {
+    Fire(Vec<Projectile>),
+    Summon(EnemyType, usize),
+    Announce(String),
+}
+
+impl BossState {
+    pub fn new(phases: Vec<Phase>) -> Self {
+        let timer = phases.first().map_or(0.0, |phase| phase.interval);
+        BossState {
+            phases,
+            phase: 0,
+            pattern: 0,
+            timer,
+            windup: None,
+            charge: None,
+        }
+    }
+    // true while the boss is moving under its own power and shouldn't be steered
+    pub fn busy(&self) -> bool {
+        self.windup.is_some() || self.charge.is_some()
+    }
+}
+
+// Runs the boss's phase logic for one tick: switching phases at HP thresholds, cycling through
+// the current phase's patterns, and carrying out a charge
+pub fn tick(boss: &mut Object, dt: f64, target: &Object, obstacles: &[Rect]) -> Vec<BossEffect> {
+    let mut effects = Vec::new();
+    let fraction = boss.hp / boss.max_hp;
+    let (x, y) = (boss.x, boss.y);
+    let (damage_type, crit_chance) = (boss.damage_type, boss.crit_chance);
+
+    let state = match boss.boss.as_mut() {
+        Some(state) => state,
+        None => return effects,
+    };
+
+    // skip straight past any phases a big hit has jumped over
+    let mut next = state.phase;
+    while next + 1 < state.phases.len() && fraction <= state.phases[next + 1].below {
+        next += 1;
+    }
+    if next != state.phase {
+        state.phase = next;
+        state.pattern = 0;
+        state.timer = 0.0;
+        if let Some(text) = state.phases[next].announce.clone() {
+            effects.push(BossEffect::Announce(text));
+        }
+    }
+
+    let mut charge_step = None;
+    if let Some(((vx, vy), remaining)) = state.charge.as_mut() {
+        *remaining -= dt;
+        charge_step = Some((*vx * dt, *vy * dt));
+        if *remaining <= 0.0 {
+            state.charge = None;
+        }
+    } else if let Some(windup) = state.windup.as_mut() {
+        *windup -= dt;
+        if *windup <= 0.0 {
+            state.windup = None;
+            if let Some(Pattern::Charge { speed, duration }) = state.phases.get(state.phase)
+                .and_then(|phase| phase.patterns.get(state.pattern))
+            {
+                let (dx, dy) = (target.x - x, target.y - y);
+                let length = (dx.powf(2.0) + dy.powf(2.0)).sqrt().max(f64::EPSILON);
+                state.charge = Some(((dx / length * speed, dy / length * speed), *duration));
+            }
+            advance_pattern(state);
+        }
+    } else if let Some(phase) = state.phases.get(state.phase) {
+        state.timer -= dt;
+        if state.timer <= 0.0 && !phase.patterns.is_empty() {
+            state.timer = phase.interval;
+            match phase.patterns[state.pattern % phase.patterns.len()].clone() {
+                Pattern::Charge { .. } => {
+                    // the pattern moves on once the charge actually starts
+                    state.windup = Some(CHARGE_WINDUP);
+                },
+                Pattern::RadialBurst { count, damage } => {
+                    let damage = Damage::new(damage, damage_type).with_crit(crit_chance);
+                    let shots = (0..count).map(|i| {
+                        let angle = TAU * i as f64 / count.max(1) as f64;
+                        Projectile::aimed((x, y), (x + angle.cos(), y + angle.sin()), damage)
+                    }).collect();
+                    effects.push(BossEffect::Fire(shots));
+                    advance_pattern(state);
+                },
+                Pattern::Summon { enemy, count } => {
+                    effects.push(BossEffect::Summon(enemy, count));
+                    advance_pattern(state);
+                },
+            }
+        }
+    }
+
+    if let Some((dx, dy)) = charge_step {
+        boss.move_by(dx, dy, obstacles);
+    }
+
+    effects
+}
+
+fn advance_pattern(state: &mut BossState) {
+    let count = state.phases.get(state.phase).map_or(1, |phase| phase.patterns.len().max(1));
+    state.pattern = (state.pattern + 1) % count;
+}
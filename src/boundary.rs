@@ -0,0 +1,134 @@
+use image::{
+    Rgb,
+    RgbImage,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Object;
+
+const STORM_TINT: [u8; 3] = [120,40,160];
+
+// What happens at the edges of the arena
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Boundary {
+    // the edges are walls
+    #[default]
+    Clamp,
+    // leaving one edge brings you in at the opposite one, shots included
+    Wrap,
+    // the edges are walls, and a circle closing in on the middle hurts anything outside it
+    Storm(Storm),
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
+pub struct Storm {
+    // seconds before it starts closing
+    #[serde(default)]
+    pub delay: f64,
+    // seconds it takes to close from the corners down to `radius`
+    pub duration: f64,
+    // the radius it stops at, in pixels
+    pub radius: f64,
+    // damage per second to anything outside
+    pub dps: f64,
+}
+
+impl Storm {
+    // The safe radius `elapsed` seconds into the level. It starts out just reaching the corners.
+    pub fn radius(&self, elapsed: f64, width: usize, height: usize) -> f64 {
+        let start = (width as f64).hypot(height as f64) / 2.0;
+        let progress = ((elapsed - self.delay) / self.duration.max(f64::EPSILON)).clamp(0.0, 1.0);
+        start + (self.radius - start) * progress
+    }
+}
+
+impl Boundary {
+    // Brings an object that has strayed past the edges back inside the arena. Clamping also
+    // kills whatever velocity was carrying it into the edge.
+    pub fn confine(&self, object: &mut Object, width: usize, height: usize) {
+        let (w, h) = (width as f64, height as f64);
+
+        match self {
+            Boundary::Wrap => {
+                object.x = object.x.rem_euclid(w);
+                object.y = object.y.rem_euclid(h);
+            },
+            Boundary::Clamp | Boundary::Storm(_) => {
+                let half = (object.size / 2.0).min(w / 2.0).min(h / 2.0);
+                let (x, y) = (object.x.clamp(half, w - half), object.y.clamp(half, h - half));
+                if x != object.x {
+                    object.velocity.0 = 0.0;
+                    object.knockback.0 = 0.0;
+                }
+                if y != object.y {
+                    object.velocity.1 = 0.0;
+                    object.knockback.1 = 0.0;
+                }
+                object.x = x;
+                object.y = y;
+            },
+        }
+    }
+    // Drains `dps * dt` from every object outside the storm. The storm ignores invulnerability
+    // and doesn't knock anything back.
+    pub fn storm_damage<'a>(&self, objects: impl Iterator<Item = &'a mut Object>, elapsed: f64, dt: f64, width: usize, height: usize) {
+        let Boundary::Storm(storm) = self else {
+            return;
+        };
+        let radius = storm.radius(elapsed, width, height);
+        let (cx, cy) = (width as f64 / 2.0, height as f64 / 2.0);
+
+        for object in objects {
+            if (object.x - cx).hypot(object.y - cy) > radius {
+                object.hp -= storm.dps * dt;
+            }
+        }
+    }
+    // Where to draw something centred on (x, y) so that, with wrap-around, the part hanging over
+    // an edge shows up at the opposite one. Always includes (x, y) itself.
+    pub fn draw_positions(&self, x: f64, y: f64, half: f64, width: usize, height: usize) -> Vec<(f64, f64)> {
+        if *self != Boundary::Wrap {
+            return vec![(x, y)];
+        }
+        let (w, h) = (width as f64, height as f64);
+
+        let mut xs = vec![x];
+        if x - half < 0.0 {
+            xs.push(x + w);
+        }
+        if x + half > w {
+            xs.push(x - w);
+        }
+        let mut ys = vec![y];
+        if y - half < 0.0 {
+            ys.push(y + h);
+        }
+        if y + half > h {
+            ys.push(y - h);
+        }
+
+        xs.iter().flat_map(|x| ys.iter().map(move |y| (*x, *y))).collect()
+    }
+    // Tints everything outside the storm
+    pub fn draw_storm(&self, image: &mut RgbImage, elapsed: f64) {
+        let Boundary::Storm(storm) = self else {
+            return;
+        };
+        let (width, height) = image.dimensions();
+        let radius = storm.radius(elapsed, width as usize, height as usize);
+        let (cx, cy) = (width as f64 / 2.0, height as f64 / 2.0);
+
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            if (x as f64 - cx).hypot(y as f64 - cy) > radius {
+                let Rgb([r, g, b]) = *pixel;
+                *pixel = Rgb([
+                    ((r as u16 + STORM_TINT[0] as u16) / 2) as u8,
+                    ((g as u16 + STORM_TINT[1] as u16) / 2) as u8,
+                    ((b as u16 + STORM_TINT[2] as u16) / 2) as u8,
+                ]);
+            }
+        }
+    }
+}
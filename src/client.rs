@@ -0,0 +1,210 @@
+// The other end of `server`: sends the local player's input and keeps the last few snapshots so
+// the world can be drawn slightly in the past, blended between the two snapshots either side.
+
+use std::{
+    collections::VecDeque,
+    io::{
+        self,
+        BufReader,
+    },
+    net::{
+        Shutdown,
+        TcpStream,
+    },
+    sync::{
+        Arc,
+        Mutex,
+    },
+    thread,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use crate::{
+    input::{
+        Direction,
+        Press,
+    },
+    popup::DamagePopup,
+    protocol::{
+        self,
+        ClientMessage,
+        LevelInfo,
+        ServerMessage,
+        Snapshot,
+        PROTOCOL_VERSION,
+    },
+};
+
+// how far behind the newest snapshot the client draws, a little over two server ticks so there's
+// nearly always a snapshot either side
+pub const INTERPOLATION_DELAY: Duration = Duration::from_millis(100);
+// snapshots kept for interpolating, far more than the delay needs
+const SNAPSHOT_BUFFER: usize = 16;
+
+pub struct Client {
+    pub level: LevelInfo,
+    stream: Mutex<TcpStream>,
+    // oldest first, each with when it arrived
+    snapshots: Mutex<VecDeque<(Instant, Snapshot)>>,
+    // announcements and join/leave notices not yet shown
+    messages: Mutex<Vec<String>>,
+    // damage numbers not yet picked up for drawing
+    hits: Mutex<Vec<DamagePopup>>,
+}
+
+impl Client {
+    // Connects and joins as `name`, failing if the server turns us away
+    pub fn connect(addr: &str, name: &str) -> io::Result<Arc<Client>> {
+        let stream = TcpStream::connect(addr)?;
+        let _ = stream.set_nodelay(true);
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+
+        protocol::send(&mut writer, &ClientMessage::Join { name: name.to_string(), version: PROTOCOL_VERSION })?;
+        let (id, level) = loop {
+            match protocol::receive::<ServerMessage>(&mut reader)? {
+                Some(ServerMessage::Welcome { id, level }) => break (id, level),
+                Some(ServerMessage::Rejected { reason }) => {
+                    return Err(io::Error::new(io::ErrorKind::ConnectionRefused, reason));
+                },
+                Some(_) => continue,
+                None => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "server hung up before welcoming us")),
+            }
+        };
+        println!("Joined {} as player {id}", level.name);
+
+        let client = Arc::new(Client {
+            level,
+            stream: Mutex::new(writer),
+            snapshots: Mutex::new(VecDeque::new()),
+            messages: Mutex::new(Vec::new()),
+            hits: Mutex::new(Vec::new()),
+        });
+
+        let _client = client.clone();
+        thread::spawn(move || {
+            loop {
+                let message = match protocol::receive::<ServerMessage>(&mut reader) {
+                    Ok(Some(message)) => message,
+                    Ok(None) => break,
+                    Err(e) => {
+                        println!("Lost the server: {e}");
+                        break;
+                    },
+                };
+                match message {
+                    ServerMessage::Snapshot(snapshot) => {
+                        let mut snapshots = loop {
+                            if let Ok(lock) = _client.snapshots.lock() {
+                                break lock;
+                            }
+                        };
+                        snapshots.push_back((Instant::now(), snapshot));
+                        while snapshots.len() > SNAPSHOT_BUFFER {
+                            snapshots.pop_front();
+                        }
+                    },
+                    ServerMessage::Announce { text } => _client.push_message(text),
+                    ServerMessage::Joined { name, .. } => _client.push_message(format!("{name} joined")),
+                    ServerMessage::Left { name, .. } => _client.push_message(format!("{name} left")),
+                    ServerMessage::Damaged { player, x, y, hit } => {
+                        let mut hits = loop {
+                            if let Ok(lock) = _client.hits.lock() {
+                                break lock;
+                            }
+                        };
+                        hits.push(DamagePopup::new(player, x, y, hit));
+                    },
+                    ServerMessage::Welcome { .. } | ServerMessage::Rejected { .. } => {},
+                }
+            }
+            _client.push_message("Disconnected from the server".to_string());
+        });
+
+        Ok(client)
+    }
+    fn push_message(&self, text: String) {
+        println!("{text}");
+        let mut messages = loop {
+            if let Ok(lock) = self.messages.lock() {
+                break lock;
+            }
+        };
+        messages.push(text);
+    }
+    fn send(&self, message: &ClientMessage) {
+        let mut stream = loop {
+            if let Ok(lock) = self.stream.lock() {
+                break lock;
+            }
+        };
+        if let Err(e) = protocol::send(&mut *stream, message) {
+            println!("Warning: failed to send to the server: {e}");
+        }
+    }
+    pub fn input(&self, direction: Direction, press: Press) {
+        self.send(&ClientMessage::Input { direction, press });
+    }
+    pub fn attack(&self) {
+        self.send(&ClientMessage::Attack);
+    }
+    pub fn choose(&self, upgrade: usize) {
+        self.send(&ClientMessage::Choose { upgrade });
+    }
+    pub fn leave(&self) {
+        self.send(&ClientMessage::Leave);
+        if let Ok(stream) = self.stream.lock() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+    // Messages received since the last call
+    pub fn take_messages(&self) -> Vec<String> {
+        let mut messages = loop {
+            if let Ok(lock) = self.messages.lock() {
+                break lock;
+            }
+        };
+        std::mem::take(&mut *messages)
+    }
+    // Hits received since the last call, as damage numbers
+    pub fn take_hits(&self) -> Vec<DamagePopup> {
+        let mut hits = loop {
+            if let Ok(lock) = self.hits.lock() {
+                break lock;
+            }
+        };
+        std::mem::take(&mut *hits)
+    }
+    // The world as it was `INTERPOLATION_DELAY` ago
+    pub fn snapshot(&self) -> Option<Snapshot> {
+        self.snapshot_at(Instant::now().checked_sub(INTERPOLATION_DELAY)?)
+    }
+    // The world as it was at `time`, going by when snapshots arrived. Before the oldest one that's
+    // the oldest one, and past the newest it's the newest, rather than guessing ahead.
+    pub fn snapshot_at(&self, time: Instant) -> Option<Snapshot> {
+        let snapshots = loop {
+            if let Ok(lock) = self.snapshots.lock() {
+                break lock;
+            }
+        };
+
+        let after = snapshots.iter().position(|(arrived, _)| *arrived > time);
+        match after {
+            Some(0) => snapshots.front().map(|(_, snapshot)| snapshot.clone()),
+            Some(idx) => {
+                let (from_time, from) = &snapshots[idx - 1];
+                let (to_time, to) = &snapshots[idx];
+                let span = to_time.duration_since(*from_time).as_secs_f64();
+                let alpha = match span > 0.0 {
+                    true => time.duration_since(*from_time).as_secs_f64() / span,
+                    false => 1.0,
+                };
+                Some(from.lerp(to, alpha.clamp(0.0, 1.0)))
+            },
+            None => snapshots.back().map(|(_, snapshot)| snapshot.clone()),
+        }
+    }
+}
@@ -0,0 +1,257 @@
+// Narrow-phase collision between simple shapes. Shapes are centred on the collider's position, and
+// touching edges don't count as a collision; only actual overlap does.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    Aabb { half_w: f64, half_h: f64 },
+    Circle { radius: f64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Collider {
+    pub x: f64,
+    pub y: f64,
+    pub shape: Shape,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    // unit vector pointing from the first collider towards the second
+    pub normal: (f64, f64),
+    // how far the second collider has to move along `normal` to stop overlapping
+    pub depth: f64,
+}
+
+impl Collider {
+    pub fn aabb(x: f64, y: f64, half_w: f64, half_h: f64) -> Self {
+        Collider { x, y, shape: Shape::Aabb { half_w, half_h } }
+    }
+    pub fn circle(x: f64, y: f64, radius: f64) -> Self {
+        Collider { x, y, shape: Shape::Circle { radius } }
+    }
+    pub fn contact(&self, other: &Collider) -> Option<Contact> {
+        use Shape::*;
+
+        match (self.shape, other.shape) {
+            (Aabb { half_w: aw, half_h: ah }, Aabb { half_w: bw, half_h: bh }) => {
+                aabb_aabb((self.x, self.y), (aw, ah), (other.x, other.y), (bw, bh))
+            },
+            (Circle { radius: ar }, Circle { radius: br }) => {
+                circle_circle((self.x, self.y), ar, (other.x, other.y), br)
+            },
+            (Aabb { half_w, half_h }, Circle { radius }) => {
+                aabb_circle((self.x, self.y), (half_w, half_h), (other.x, other.y), radius)
+            },
+            (Circle { radius }, Aabb { half_w, half_h }) => {
+                aabb_circle((other.x, other.y), (half_w, half_h), (self.x, self.y), radius)
+                    .map(flip)
+            },
+        }
+    }
+    pub fn overlaps(&self, other: &Collider) -> bool {
+        self.contact(other).is_some()
+    }
+}
+
+fn flip(contact: Contact) -> Contact {
+    Contact {
+        normal: (-contact.normal.0, -contact.normal.1),
+        depth: contact.depth,
+    }
+}
+
+// direction for `delta` along one axis, picking positive when the centres line up exactly
+fn sign(delta: f64) -> f64 {
+    if delta < 0.0 { -1.0 } else { 1.0 }
+}
+
+fn aabb_aabb(a: (f64, f64), a_half: (f64, f64), b: (f64, f64), b_half: (f64, f64)) -> Option<Contact> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let overlap_x = a_half.0 + b_half.0 - dx.abs();
+    let overlap_y = a_half.1 + b_half.1 - dy.abs();
+
+    if overlap_x <= 0.0 || overlap_y <= 0.0 {
+        return None;
+    }
+
+    // separate along whichever axis needs the smaller push
+    if overlap_x <= overlap_y {
+        Some(Contact { normal: (sign(dx), 0.0), depth: overlap_x })
+    } else {
+        Some(Contact { normal: (0.0, sign(dy)), depth: overlap_y })
+    }
+}
+
+fn circle_circle(a: (f64, f64), a_radius: f64, b: (f64, f64), b_radius: f64) -> Option<Contact> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let distance = (dx.powf(2.0) + dy.powf(2.0)).sqrt();
+    let depth = a_radius + b_radius - distance;
+
+    if depth <= 0.0 {
+        return None;
+    }
+
+    let normal = if distance > f64::EPSILON {
+        (dx / distance, dy / distance)
+    } else {
+        (1.0, 0.0)
+    };
+
+    Some(Contact { normal, depth })
+}
+
+fn aabb_circle(a: (f64, f64), a_half: (f64, f64), b: (f64, f64), radius: f64) -> Option<Contact> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+
+    // the circle's centre is inside the box or on its edge, where there's no closest point to
+    // push away from, so push it out through the nearest face
+    if dx.abs() <= a_half.0 && dy.abs() <= a_half.1 {
+        let exit_x = a_half.0 - dx.abs();
+        let exit_y = a_half.1 - dy.abs();
+        let contact = if exit_x <= exit_y {
+            Contact { normal: (sign(dx), 0.0), depth: exit_x + radius }
+        } else {
+            Contact { normal: (0.0, sign(dy)), depth: exit_y + radius }
+        };
+        return (contact.depth > 0.0).then_some(contact);
+    }
+
+    let closest = (dx.clamp(-a_half.0, a_half.0), dy.clamp(-a_half.1, a_half.1));
+    let (ox, oy) = (dx - closest.0, dy - closest.1);
+    let distance = (ox.powf(2.0) + oy.powf(2.0)).sqrt();
+    let depth = radius - distance;
+
+    if depth <= 0.0 {
+        return None;
+    }
+
+    Some(Contact { normal: (ox / distance, oy / distance), depth })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    fn assert_contact(contact: Option<Contact>, normal: (f64, f64), depth: f64) {
+        let contact = contact.expect("expected a contact");
+        assert!(close(contact.normal.0, normal.0) && close(contact.normal.1, normal.1), "normal {:?}, expected {:?}", contact.normal, normal);
+        assert!(close(contact.depth, depth), "depth {}, expected {}", contact.depth, depth);
+    }
+
+    #[test]
+    fn box_box_overlap_separates_along_the_shallower_axis() {
+        let a = Collider::aabb(0.0, 0.0, 5.0, 5.0);
+        let b = Collider::aabb(8.0, 1.0, 5.0, 5.0);
+        assert_contact(a.contact(&b), (1.0, 0.0), 2.0);
+        assert_contact(b.contact(&a), (-1.0, 0.0), 2.0);
+
+        let c = Collider::aabb(1.0, -9.0, 5.0, 5.0);
+        assert_contact(a.contact(&c), (0.0, -1.0), 1.0);
+    }
+
+    #[test]
+    fn box_box_apart_or_touching() {
+        let a = Collider::aabb(0.0, 0.0, 5.0, 5.0);
+        assert_eq!(a.contact(&Collider::aabb(20.0, 0.0, 5.0, 5.0)), None);
+        // edges meeting exactly, side by side and corner to corner
+        assert_eq!(a.contact(&Collider::aabb(10.0, 0.0, 5.0, 5.0)), None);
+        assert_eq!(a.contact(&Collider::aabb(10.0, 10.0, 5.0, 5.0)), None);
+    }
+
+    #[test]
+    fn box_box_containment() {
+        let outer = Collider::aabb(0.0, 0.0, 10.0, 10.0);
+        let inner = Collider::aabb(2.0, 0.0, 1.0, 1.0);
+        assert_contact(outer.contact(&inner), (1.0, 0.0), 9.0);
+
+        // same centre, the push still has a direction
+        let centred = Collider::aabb(0.0, 0.0, 1.0, 1.0);
+        assert_contact(outer.contact(&centred), (1.0, 0.0), 11.0);
+    }
+
+    #[test]
+    fn circle_circle_overlap() {
+        let a = Collider::circle(0.0, 0.0, 3.0);
+        let b = Collider::circle(3.0, 4.0, 3.0);
+        assert_contact(a.contact(&b), (0.6, 0.8), 1.0);
+        assert_contact(b.contact(&a), (-0.6, -0.8), 1.0);
+    }
+
+    #[test]
+    fn circle_circle_apart_or_touching() {
+        let a = Collider::circle(0.0, 0.0, 3.0);
+        assert_eq!(a.contact(&Collider::circle(10.0, 0.0, 3.0)), None);
+        assert_eq!(a.contact(&Collider::circle(6.0, 0.0, 3.0)), None);
+    }
+
+    #[test]
+    fn circle_circle_containment() {
+        let outer = Collider::circle(0.0, 0.0, 10.0);
+        assert_contact(outer.contact(&Collider::circle(0.0, 2.0, 1.0)), (0.0, 1.0), 9.0);
+        // same centre
+        assert_contact(outer.contact(&Collider::circle(0.0, 0.0, 1.0)), (1.0, 0.0), 11.0);
+    }
+
+    #[test]
+    fn box_circle_against_a_face_and_a_corner() {
+        let a = Collider::aabb(0.0, 0.0, 5.0, 5.0);
+        assert_contact(a.contact(&Collider::circle(6.0, 1.0, 2.0)), (1.0, 0.0), 1.0);
+        assert_contact(a.contact(&Collider::circle(0.0, -6.0, 2.0)), (0.0, -1.0), 1.0);
+
+        let diagonal = 0.5f64.sqrt();
+        let corner = Collider::circle(6.0, 6.0, 2.0);
+        assert_contact(a.contact(&corner), (diagonal, diagonal), 2.0 - 2.0f64.sqrt());
+        // the other way round the normal flips
+        assert_contact(corner.contact(&a), (-diagonal, -diagonal), 2.0 - 2.0f64.sqrt());
+    }
+
+    #[test]
+    fn box_circle_apart_or_touching() {
+        let a = Collider::aabb(0.0, 0.0, 5.0, 5.0);
+        assert_eq!(a.contact(&Collider::circle(10.0, 0.0, 2.0)), None);
+        assert_eq!(a.contact(&Collider::circle(7.0, 0.0, 2.0)), None);
+        // just clear of the corner, though inside both face lines
+        assert_eq!(a.contact(&Collider::circle(6.5, 6.5, 2.0)), None);
+    }
+
+    #[test]
+    fn box_circle_centre_on_the_edge() {
+        let a = Collider::aabb(0.0, 0.0, 5.0, 5.0);
+        assert_contact(a.contact(&Collider::circle(5.0, 0.0, 2.0)), (1.0, 0.0), 2.0);
+        assert_contact(a.contact(&Collider::circle(0.0, -5.0, 2.0)), (0.0, -1.0), 2.0);
+        assert_contact(a.contact(&Collider::circle(5.0, 5.0, 2.0)), (1.0, 0.0), 2.0);
+    }
+
+    #[test]
+    fn box_circle_containment() {
+        let a = Collider::aabb(0.0, 0.0, 10.0, 10.0);
+        assert_contact(a.contact(&Collider::circle(0.0, 7.0, 1.0)), (0.0, 1.0), 4.0);
+        // a circle bigger than the box around it
+        let b = Collider::aabb(0.0, 0.0, 1.0, 1.0);
+        assert_contact(b.contact(&Collider::circle(0.0, 0.0, 10.0)), (1.0, 0.0), 11.0);
+    }
+
+    #[test]
+    fn zero_size_shapes() {
+        let point_box = Collider::aabb(0.0, 0.0, 0.0, 0.0);
+        let point_circle = Collider::circle(0.0, 0.0, 0.0);
+
+        // points have nothing to overlap with each other, even on top of each other
+        assert_eq!(point_box.contact(&Collider::aabb(0.0, 0.0, 0.0, 0.0)), None);
+        assert_eq!(point_circle.contact(&Collider::circle(0.0, 0.0, 0.0)), None);
+        assert_eq!(point_box.contact(&point_circle), None);
+        assert_eq!(point_circle.contact(&point_box), None);
+
+        // but one inside a real shape still collides, and never with a NaN
+        let solid = Collider::aabb(1.0, 0.0, 5.0, 5.0);
+        assert_contact(solid.contact(&point_box), (-1.0, 0.0), 4.0);
+        assert_contact(solid.contact(&Collider::circle(1.0, 0.0, 0.0)), (1.0, 0.0), 5.0);
+        assert_contact(point_box.contact(&Collider::circle(0.0, 2.0, 3.0)), (0.0, 1.0), 1.0);
+        assert_contact(point_circle.contact(&Collider::circle(0.0, 0.0, 3.0)), (1.0, 0.0), 3.0);
+        assert_eq!(point_box.contact(&Collider::circle(3.0, 0.0, 3.0)), None);
+    }
+}
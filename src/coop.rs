@@ -0,0 +1,59 @@
+// Local co-op: an optional second player on the same device, driven by its own row of buttons
+
+use serde::{Deserialize, Serialize};
+
+use crate::args;
+
+// the second player's body, so the two can be told apart
+pub const PARTNER_COLOR: [u8; 3] = [0,90,255];
+// how far right of the first player the second one starts, in pixels
+pub const PARTNER_OFFSET: f64 = 20.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Coop {
+    // one player
+    #[default]
+    Off,
+    // two players drawing on one HP pool, which loses the level when it runs out
+    Shared,
+    // two players with their own HP; a player who drops is out until the level ends, and the
+    // level is lost once both are down
+    Separate,
+}
+
+impl Coop {
+    pub const ALL: [Coop; 3] = [Coop::Off, Coop::Shared, Coop::Separate];
+
+    pub fn name(&self) -> &'static str {
+        use Coop::*;
+        match self {
+            Off => "off",
+            Shared => "shared",
+            Separate => "separate",
+        }
+    }
+    pub fn label(&self) -> &'static str {
+        use Coop::*;
+        match self {
+            Off => "1",
+            Shared => "2, shared HP",
+            Separate => "2, separate HP",
+        }
+    }
+    pub fn next(&self) -> Self {
+        let idx = Coop::ALL.iter().position(|coop| coop == self).unwrap_or(0);
+        Coop::ALL[(idx + 1) % Coop::ALL.len()]
+    }
+    // Reads `--coop <shared|separate|off>` from the command line
+    pub fn from_args() -> Self {
+        match args::value(&["--coop"]) {
+            Some(name) => Coop::ALL.into_iter()
+                .find(|coop| coop.name().eq_ignore_ascii_case(&name))
+                .unwrap_or_else(|| {
+                    println!("Warning: unknown co-op mode \"{name}\" (expected off, shared or separate)");
+                    Coop::default()
+                }),
+            None => Coop::default(),
+        }
+    }
+}
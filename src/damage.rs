@@ -0,0 +1,85 @@
+use rand::Rng;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+// how much harder a critical hit lands
+pub const CRIT_MULTIPLIER: f64 = 2.0;
+// armour can't take a hit below this fraction of what got past the resistances
+pub const MIN_DAMAGE_FRACTION: f64 = 0.2;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DamageType {
+    #[default]
+    Kinetic,
+    Fire,
+    Ice,
+    Shock,
+}
+
+impl DamageType {
+    pub fn name(&self) -> &'static str {
+        use DamageType::*;
+        match self {
+            Kinetic => "kinetic",
+            Fire => "fire",
+            Ice => "ice",
+            Shock => "shock",
+        }
+    }
+}
+
+// A hit on its way in, before the target's defences have had a say
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Damage {
+    pub amount: f64,
+    pub kind: DamageType,
+    // chance from 0 to 1 that it lands as a critical hit
+    pub crit_chance: f64,
+}
+
+impl Damage {
+    pub fn new(amount: f64, kind: DamageType) -> Self {
+        Damage { amount, kind, crit_chance: 0.0 }
+    }
+    pub fn with_crit(mut self, crit_chance: f64) -> Self {
+        self.crit_chance = crit_chance;
+        self
+    }
+}
+
+// What a hit came to once it landed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Hit {
+    pub amount: f64,
+    pub kind: DamageType,
+    pub crit: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Defence {
+    // flat reduction taken off every hit
+    pub armour: f64,
+    // multipliers by damage type: under 1 resists, over 1 is a weakness, 0 is immune. Types that
+    // aren't listed take full damage.
+    pub resistances: HashMap<DamageType, f64>,
+}
+
+impl Defence {
+    // The damage pipeline: roll for a crit, apply the resistance for the damage type, then take
+    // off armour
+    pub fn resolve(&self, damage: Damage, rng: &mut impl Rng) -> Hit {
+        let crit = damage.crit_chance > 0.0 && rng.gen_bool(damage.crit_chance.min(1.0));
+        let mut amount = damage.amount;
+        if crit {
+            amount *= CRIT_MULTIPLIER;
+        }
+
+        amount *= self.resistances.get(&damage.kind).copied().unwrap_or(1.0);
+        amount = (amount - self.armour).max(amount * MIN_DAMAGE_FRACTION).max(0.0);
+
+        Hit { amount, kind: damage.kind, crit }
+    }
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+use crate::args;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+// Multipliers applied to every enemy archetype and to the wave spawner
+#[derive(Debug, Clone, Copy)]
+pub struct Profile {
+    pub hp: f64,
+    pub speed: f64,
+    // contact damage, shots and boss bursts
+    pub damage: f64,
+    // waves spawn their enemies this many times as fast
+    pub spawn_rate: f64,
+    // waves are picked from the mix this many waves later (or earlier, if negative)
+    pub mix_shift: i32,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn profile(&self) -> Profile {
+        use Difficulty::*;
+        match self {
+            Easy => Profile { hp: 0.7, speed: 0.85, damage: 0.6, spawn_rate: 0.75, mix_shift: -2 },
+            Normal => Profile { hp: 1.0, speed: 1.0, damage: 1.0, spawn_rate: 1.0, mix_shift: 0 },
+            Hard => Profile { hp: 1.4, speed: 1.15, damage: 1.5, spawn_rate: 1.4, mix_shift: 2 },
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        use Difficulty::*;
+        match self {
+            Easy => "easy",
+            Normal => "normal",
+            Hard => "hard",
+        }
+    }
+    pub fn parse(name: &str) -> Option<Self> {
+        Difficulty::ALL.into_iter().find(|difficulty| difficulty.name().eq_ignore_ascii_case(name))
+    }
+    // the next one along, wrapping back to easy, for cycling through them on the title screen
+    pub fn next(&self) -> Self {
+        let idx = Difficulty::ALL.iter().position(|difficulty| difficulty == self).unwrap_or(0);
+        Difficulty::ALL[(idx + 1) % Difficulty::ALL.len()]
+    }
+    // Reads `--difficulty <name>` or `-d <name>` from the command line. Anything unrecognised is
+    // reported and ignored.
+    pub fn from_args() -> Self {
+        match args::value(&["--difficulty", "-d"]) {
+            Some(name) => Difficulty::parse(&name).unwrap_or_else(|| {
+                println!("Warning: unknown difficulty \"{name}\" (expected easy, normal or hard)");
+                Difficulty::default()
+            }),
+            None => Difficulty::default(),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use image::RgbImage;
+
+// 5x7 capitals, digits and a little punctuation for text drawn onto the game view. Lower case is
+// drawn as upper case and anything else as a question mark.
+pub const GLYPH_WIDTH: u32 = 5;
+pub const GLYPH_HEIGHT: u32 = 7;
+
+fn glyph(c: char) -> [&'static str; 7] {
+    match c.to_ascii_uppercase() {
+        'A' => [".###.", "#...#", "#...#", "#####", "#...#", "#...#", "#...#"],
+        'B' => ["####.", "#...#", "#...#", "####.", "#...#", "#...#", "####."],
+        'C' => [".###.", "#...#", "#....", "#....", "#....", "#...#", ".###."],
+        'D' => ["####.", "#...#", "#...#", "#...#", "#...#", "#...#", "####."],
+        'E' => ["#####", "#....", "#....", "####.", "#....", "#....", "#####"],
+        'F' => ["#####", "#....", "#....", "####.", "#....", "#....", "#...."],
+        'G' => [".###.", "#...#", "#....", "#.###", "#...#", "#...#", ".####"],
+        'H' => ["#...#", "#...#", "#...#", "#####", "#...#", "#...#", "#...#"],
+        'I' => [".###.", "..#..", "..#..", "..#..", "..#..", "..#..", ".###."],
+        'J' => ["..###", "...#.", "...#.", "...#.", "...#.", "#..#.", ".##.."],
+        'K' => ["#...#", "#..#.", "#.#..", "##...", "#.#..", "#..#.", "#...#"],
+        'L' => ["#....", "#....", "#....", "#....", "#....", "#....", "#####"],
+        'M' => ["#...#", "##.##", "#.#.#", "#.#.#", "#...#", "#...#", "#...#"],
+        'N' => ["#...#", "#...#", "##..#", "#.#.#", "#..##", "#...#", "#...#"],
+        'O' => [".###.", "#...#", "#...#", "#...#", "#...#", "#...#", ".###."],
+        'P' => ["####.", "#...#", "#...#", "####.", "#....", "#....", "#...."],
+        'Q' => [".###.", "#...#", "#...#", "#...#", "#.#.#", "#..#.", ".##.#"],
+        'R' => ["####.", "#...#", "#...#", "####.", "#.#..", "#..#.", "#...#"],
+        'S' => [".####", "#....", "#....", ".###.", "....#", "....#", "####."],
+        'T' => ["#####", "..#..", "..#..", "..#..", "..#..", "..#..", "..#.."],
+        'U' => ["#...#", "#...#", "#...#", "#...#", "#...#", "#...#", ".###."],
+        'V' => ["#...#", "#...#", "#...#", "#...#", "#...#", ".#.#.", "..#.."],
+        'W' => ["#...#", "#...#", "#...#", "#.#.#", "#.#.#", "#.#.#", ".#.#."],
+        'X' => ["#...#", "#...#", ".#.#.", "..#..", ".#.#.", "#...#", "#...#"],
+        'Y' => ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#..", "..#.."],
+        'Z' => ["#####", "....#", "...#.", "..#..", ".#...", "#....", "#####"],
+        '0' => [".###.", "#...#", "#..##", "#.#.#", "##..#", "#...#", ".###."],
+        '1' => ["..#..", ".##..", "..#..", "..#..", "..#..", "..#..", ".###."],
+        '2' => [".###.", "#...#", "....#", "...#.", "..#..", ".#...", "#####"],
+        '3' => ["#####", "...#.", "..#..", "...#.", "....#", "#...#", ".###."],
+        '4' => ["...#.", "..##.", ".#.#.", "#..#.", "#####", "...#.", "...#."],
+        '5' => ["#####", "#....", "####.", "....#", "....#", "#...#", ".###."],
+        '6' => ["..##.", ".#...", "#....", "####.", "#...#", "#...#", ".###."],
+        '7' => ["#####", "....#", "...#.", "..#..", ".#...", ".#...", ".#..."],
+        '8' => [".###.", "#...#", "#...#", ".###.", "#...#", "#...#", ".###."],
+        '9' => [".###.", "#...#", "#...#", ".####", "....#", "...#.", ".##.."],
+        ' ' => [".....", ".....", ".....", ".....", ".....", ".....", "....."],
+        '!' => ["..#..", "..#..", "..#..", "..#..", "..#..", ".....", "..#.."],
+        '.' => [".....", ".....", ".....", ".....", ".....", ".....", "..#.."],
+        ':' => [".....", "..#..", ".....", ".....", ".....", "..#..", "....."],
+        '-' => [".....", ".....", ".....", "#####", ".....", ".....", "....."],
+        '\'' => ["..#..", "..#..", ".....", ".....", ".....", ".....", "....."],
+        _ => [".###.", "#...#", "....#", "...#.", "..#..", ".....", "..#.."],
+    }
+}
+
+// Width in pixels of `text` at `scale` pixels to the dot, with one dot between letters
+pub fn text_width(text: &str, scale: u32) -> u32 {
+    let count = text.chars().count() as u32;
+    (count * (GLYPH_WIDTH + 1)).saturating_sub(1) * scale
+}
+
+// Writes `text` with its top left corner at (`left`, `top`). Whatever runs off the image is
+// clipped.
+pub fn draw_text(image: &mut RgbImage, text: &str, left: u32, top: u32, scale: u32, color: [u8; 3]) {
+    let (width, height) = image.dimensions();
+
+    for (i, c) in text.chars().enumerate() {
+        let glyph_left = left + i as u32 * (GLYPH_WIDTH + 1) * scale;
+        for (row, line) in glyph(c).iter().enumerate() {
+            for (col, dot) in line.chars().enumerate() {
+                if dot != '#' {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let (x, y) = (glyph_left + col as u32 * scale + dx, top + row as u32 * scale + dy);
+                        if x < width && y < height {
+                            image.put_pixel(x, y, color.into());
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,104 @@
+// Held-direction tracking for the player. Touch events give proper press/release pairs; plain
+// clicks only say that a button was tapped, so a tap holds its direction for `TAP_HOLD` seconds
+// and repeated taps keep the player moving.
+
+use serde::{Deserialize, Serialize};
+
+pub const TAP_HOLD: f64 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    fn idx(&self) -> usize {
+        use Direction::*;
+        match self {
+            Up => 0,
+            Down => 1,
+            Left => 2,
+            Right => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Press {
+    Down,
+    Up,
+    Tap,
+}
+
+impl Press {
+    // Reads a TGui event's value: touch events carry an "action", anything else is a click. None
+    // for a finger sliding about on the button, which changes nothing.
+    pub fn from_event_value(value: &serde_json::Value) -> Option<Self> {
+        match value.get("action").and_then(|action| action.as_str()) {
+            Some("down") => Some(Press::Down),
+            Some("up") | Some("cancel") => Some(Press::Up),
+            Some(_) => None,
+            None => Some(Press::Tap),
+        }
+    }
+}
+
+// Whether a TGui event is the activity going into the background. Termux:GUI's pause, stop and
+// destroy events are the ones whose value carries "finishing".
+pub fn is_backgrounded(value: &serde_json::Value) -> bool {
+    value.get("finishing").is_some()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct InputState {
+    held: [bool; 4],
+    // seconds left on each direction's tap
+    taps: [f64; 4],
+}
+
+impl InputState {
+    pub fn apply(&mut self, direction: Direction, press: Press) -> &mut Self {
+        let idx = direction.idx();
+        match press {
+            Press::Down => self.held[idx] = true,
+            Press::Up => {
+                self.held[idx] = false;
+                self.taps[idx] = 0.0;
+            },
+            Press::Tap => self.taps[idx] = TAP_HOLD,
+        }
+        self
+    }
+    pub fn tick(&mut self, dt: f64) -> &mut Self {
+        for tap in self.taps.iter_mut() {
+            *tap = (*tap - dt).max(0.0);
+        }
+        self
+    }
+    fn active(&self, direction: Direction) -> bool {
+        let idx = direction.idx();
+        self.held[idx] || self.taps[idx] > 0.0
+    }
+    // Unit vector of the held directions, so diagonals are no faster than straight lines, or
+    // zero if nothing is held (or opposite directions cancel out)
+    pub fn vector(&self) -> (f64, f64) {
+        use Direction::*;
+
+        let axis = |negative: Direction, positive: Direction| {
+            self.active(positive) as i32 as f64 - self.active(negative) as i32 as f64
+        };
+        let (x, y) = (axis(Left, Right), axis(Up, Down));
+        let length = (x.powf(2.0) + y.powf(2.0)).sqrt();
+
+        if length > 0.0 {
+            (x / length, y / length)
+        } else {
+            (0.0, 0.0)
+        }
+    }
+}
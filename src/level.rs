@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+
+use std::{
+    fs::{
+        read_dir,
+        read_to_string,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use crate::{
+    anim_data::Overlay,
+    boundary::Boundary,
+    wave::SPAWN_INSET,
+    EnemyType,
+    WIDTH,
+    HEIGHT,
+};
+
+pub const LEVEL_DIR: &str = "levels";
+
+// the narrowest a level can be: waves spawn inset from both edges, and the player has to fit
+// in between
+const MIN_SIDE: f64 = 2.0 * SPAWN_INSET + 20.0;
+
+// used when the levels directory is missing or empty
+const BUILTIN_LEVEL: &str = include_str!("../levels/01_arena.json");
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+}
+
+impl Rect {
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.w && y < self.y + self.h
+    }
+    // touching edges don't count, so something pushed flush against a wall is free to slide
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.x + other.w
+            && other.x < self.x + self.w
+            && self.y < other.y + other.h
+            && other.y < self.y + self.h
+    }
+    pub fn as_overlay(&self, buffer_height: usize, buffer_width: usize, rgb: &[u8; 3]) -> Overlay {
+        let mut overlay = Overlay::new(buffer_height, buffer_width);
+
+        for x in (self.x.max(0.0) as usize)..((self.x+self.w).max(0.0) as usize) {
+            for y in (self.y.max(0.0) as usize)..((self.y+self.h).max(0.0) as usize) {
+                let _ = overlay.set_pixel(x, y, rgb);
+            }
+        }
+
+        overlay
+    }
+}
+
+// A patch of ground that takes `dps` HP per second from anything standing in it, player or enemy
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Hazard {
+    #[serde(flatten)]
+    pub area: Rect,
+    pub dps: f64,
+}
+
+impl Hazard {
+    pub const COLOR: [u8; 3] = [200,90,20];
+}
+
+// A group of enemies that enters at a fixed point `time` seconds into the level
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Spawn {
+    pub time: f64,
+    pub enemy: EnemyType,
+    pub x: f64,
+    pub y: f64,
+    #[serde(default = "default_count")]
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaveSettings {
+    // `null` keeps the waves coming until the player dies
+    pub count: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WinCondition {
+    // stay alive for this many seconds
+    Survive(f64),
+    // every scripted spawn and wave has entered and been killed
+    KillAll,
+    // touch the level's `exit`
+    ReachExit,
+    // kill a boss, with no other boss left alive
+    DefeatBoss,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Level {
+    pub name: String,
+    #[serde(default = "default_width")]
+    pub width: usize,
+    #[serde(default = "default_height")]
+    pub height: usize,
+    #[serde(default = "default_background")]
+    pub background: [u8; 3],
+    // defaults to the middle of the arena
+    #[serde(default)]
+    pub player_start: Option<[f64; 2]>,
+    // what happens at the edges, walls by default
+    #[serde(default)]
+    pub boundary: Boundary,
+    #[serde(default)]
+    pub obstacles: Vec<Rect>,
+    #[serde(default)]
+    pub exit: Option<Rect>,
+    #[serde(default)]
+    pub hazards: Vec<Hazard>,
+    #[serde(default)]
+    pub spawns: Vec<Spawn>,
+    #[serde(default)]
+    pub waves: Option<WaveSettings>,
+    // no win condition means the level lasts until the player dies
+    #[serde(default)]
+    pub win: Option<WinCondition>,
+    // running out of time loses the level, as does dying
+    #[serde(default)]
+    pub time_limit: Option<f64>,
+}
+
+fn default_count() -> usize { 1 }
+fn default_width() -> usize { WIDTH }
+fn default_height() -> usize { HEIGHT }
+fn default_background() -> [u8; 3] { [255,255,255] }
+
+impl Level {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let level: Level = serde_json::from_str(json).map_err(|e| e.to_string())?;
+
+        if (level.width as f64) < MIN_SIDE || (level.height as f64) < MIN_SIDE {
+            return Err(format!(
+                "level \"{}\" is {}x{}, it needs to be at least {MIN_SIDE} pixels each way",
+                level.name, level.width, level.height,
+            ));
+        }
+        if level.win == Some(WinCondition::ReachExit) && level.exit.is_none() {
+            return Err(format!("level \"{}\" is won by reaching an exit but has none", level.name));
+        }
+
+        Ok(level)
+    }
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = read_to_string(path).map_err(|e| e.to_string())?;
+        Level::from_json(&json)
+    }
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+    pub fn builtin() -> Self {
+        Level::from_json(BUILTIN_LEVEL).unwrap()
+    }
+    pub fn player_start(&self) -> (f64, f64) {
+        match self.player_start {
+            Some([x, y]) => (x, y),
+            None => (self.width as f64 / 2.0, self.height as f64 / 2.0),
+        }
+    }
+}
+
+// Every level in `LEVEL_DIR` that parses, sorted by file name. Broken files are reported and
+// skipped. Falls back to the built-in arena if nothing is found.
+pub fn list_levels() -> Vec<Level> {
+    let mut paths: Vec<PathBuf> = match read_dir(LEVEL_DIR) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect(),
+        Err(e) => {
+            println!("Warning: couldn't read {LEVEL_DIR} ({e})");
+            Vec::new()
+        },
+    };
+    paths.sort();
+
+    let mut levels = Vec::new();
+    for path in paths {
+        match Level::load(&path) {
+            Ok(level) => levels.push(level),
+            Err(e) => println!("Warning: skipping {} ({e})", path.display()),
+        }
+    }
+
+    if levels.is_empty() {
+        levels.push(Level::builtin());
+    }
+
+    levels
+}
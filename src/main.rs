@@ -17,16 +17,19 @@ use tgui::{
 use interpolator::{format, Formattable, iwrite};
 
 use rand::{
-    thread_rng,
     seq::SliceRandom,
+    Rng,
 };
 
 use image::{Rgb, RgbImage};
 
+use serde::{Deserialize, Serialize};
+
 use std::{
     sync::{
         Arc,
         Mutex,
+        atomic::{AtomicU64, Ordering},
         mpsc::channel,
     },
     thread::{
@@ -44,146 +47,622 @@ use std::{
     io::Write,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
 enum Action {
+    // hits the closest target in range
     Projectile(f64),
+    // hits everything within a radius
+    Nova(f64),
 }
 
-#[derive(Debug, Clone)]
+impl Action {
+    fn scale(&mut self, factor: f64) -> &mut Self {
+        use Action::*;
+        match self {
+            Projectile(magnitude) | Nova(magnitude) => *magnitude *= factor,
+        }
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
 enum EnemyType {
     Weak,
     Medium,
-    Strong
+    Strong,
+    Boss,
 }
-#[derive(Debug, Clone)]
+
+impl EnemyType {
+    // key into the archetype file
+    fn archetype_name(&self) -> &'static str {
+        use EnemyType::*;
+        match self {
+            Weak => "weak",
+            Medium => "medium",
+            Strong => "strong",
+            Boss => "boss",
+        }
+    }
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
 enum ObjectName {
     Player,
     Enemy(EnemyType),
 }
 
-#[derive(Debug, Clone)]
+static NEXT_OBJECT_ID: AtomicU64 = AtomicU64::new(0);
+
+fn next_object_id() -> u64 {
+    NEXT_OBJECT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct Object {
+    // unique for the life of the process, so networked clients can follow an object between
+    // snapshots. Loaded objects get fresh ones.
+    #[serde(skip, default = "next_object_id")]
+    pub id: u64,
     pub name: ObjectName,
     pub x: f64,
     pub y: f64,
     pub size: f64,
     pub action: Action,
     pub recharge: f64,
+    pub cooldown: f64,
+    // an extra action and its cooldown, fired alongside `action`
+    pub secondary: Option<(Action, f64)>,
+    pub secondary_recharge: f64,
+    pub range_bonus: f64,
+    pub fire_range: f64,
+    pub telegraph: Option<f64>,
     pub hp: f64,
     pub max_hp: f64,
+    pub speed: f64,
+    pub velocity: (f64, f64),
+    pub acceleration: f64,
+    pub friction: f64,
+    pub contact_dps: f64,
+    pub knockback: (f64, f64),
+    pub invulnerable: f64,
+    pub invulnerability: f64,
+    pub buffs: Vec<Buff>,
+    pub statuses: Statuses,
+    pub defence: Defence,
+    // what its primary action deals, and how often that crits
+    pub damage_type: DamageType,
+    pub crit_chance: f64,
+    // hits that landed since the world last collected them
+    pub hits: Vec<Hit>,
+    // status effects this object's attacks inflict when they land
+    pub on_hit: Vec<StatusEffect>,
+    // phase state, for archetypes that have phases
+    pub boss: Option<BossState>,
+    pub color: [u8; 3],
+    pub behaviour: Behaviour,
 }
 
 impl Object {
     fn new(name: ObjectName, x: f64, y: f64, size: f64, max_hp: f64, action: Action) -> Self {
         Object {
+            id: next_object_id(),
             name,
             x,
             y,
             size,
             action,
             recharge: 0.0,
+            cooldown: 1.0,
+            secondary: None,
+            secondary_recharge: 0.0,
+            range_bonus: 0.0,
+            fire_range: 0.0,
+            telegraph: None,
             hp: max_hp,
             max_hp,
+            speed: 0.0,
+            velocity: (0.0, 0.0),
+            acceleration: 0.0,
+            friction: 0.0,
+            contact_dps: 0.0,
+            knockback: (0.0, 0.0),
+            invulnerable: 0.0,
+            invulnerability: 0.0,
+            buffs: Vec::new(),
+            statuses: Statuses::default(),
+            defence: Defence::default(),
+            damage_type: DamageType::Kinetic,
+            crit_chance: 0.0,
+            hits: Vec::new(),
+            on_hit: Vec::new(),
+            boss: None,
+            color: [150,150,150],
+            behaviour: Behaviour::Chase,
+        }
+    }
+    fn from_archetype(name: ObjectName, archetype: &Archetype, x: f64, y: f64) -> Self {
+        let mut object = Object::new(name, x, y, archetype.size, archetype.max_hp, archetype.action.clone());
+        object.cooldown = archetype.cooldown;
+        object.fire_range = archetype.fire_range;
+        object.speed = archetype.speed;
+        object.acceleration = archetype.acceleration;
+        object.friction = archetype.friction;
+        object.contact_dps = archetype.contact_dps;
+        object.invulnerability = archetype.invulnerability;
+        object.color = archetype.color;
+        object.behaviour = archetype.behaviour;
+        object.on_hit = archetype.on_hit.clone();
+        object.defence = Defence {
+            armour: archetype.armour,
+            resistances: archetype.resistances.clone(),
+        };
+        object.damage_type = archetype.damage_type;
+        object.crit_chance = archetype.crit_chance;
+        if !archetype.phases.is_empty() {
+            object.boss = Some(BossState::new(archetype.phases.clone()));
+        }
+        object
+    }
+    fn enemy(enemy_type: EnemyType, archetypes: &Archetypes, x: f64, y: f64, rng: &mut impl Rng) -> Self {
+        let archetype = archetypes.enemy(&enemy_type);
+        let mut enemy = Object::from_archetype(ObjectName::Enemy(enemy_type), archetype, x, y);
+        // stagger the first shots so a group spawning together doesn't fire at once
+        if enemy.cooldown > 0.0 {
+            enemy.recharge = rng.gen_range(0.0..enemy.cooldown);
+        }
+        enemy
+    }
+    // Moves one axis at a time, backing out of any obstacle hit along that axis, so running into
+    // a wall at an angle slides along it instead of stopping dead
+    fn move_by(&mut self, dx: f64, dy: f64, obstacles: &[Rect]) -> &mut Self {
+        let half = self.size / 2.0;
+
+        self.x += dx;
+        for obstacle in obstacles.iter() {
+            if !obstacle.intersects(&self.bounds()) {
+                continue;
+            }
+            if dx > 0.0 {
+                self.x = obstacle.x - half;
+            } else if dx < 0.0 {
+                self.x = obstacle.x + obstacle.w + half;
+            }
+        }
+
+        self.y += dy;
+        for obstacle in obstacles.iter() {
+            if !obstacle.intersects(&self.bounds()) {
+                continue;
+            }
+            if dy > 0.0 {
+                self.y = obstacle.y - half;
+            } else if dy < 0.0 {
+                self.y = obstacle.y + obstacle.h + half;
+            }
+        }
+
+        self
+    }
+    // Applies a hit unless the object is still invulnerable from the last one, returning whether
+    // it landed. The damage goes through the object's defences first, and what's left is recorded
+    // in `hits`. `impulse` is added to the knockback velocity, in pixels per second.
+    fn take_hit(&mut self, damage: Damage, impulse: (f64, f64), rng: &mut impl Rng) -> bool {
+        if self.invulnerable > 0.0 {
+            return false;
+        }
+
+        let hit = self.defence.resolve(damage, rng);
+        self.hp -= hit.amount;
+        self.hits.push(hit);
+        self.knockback.0 += impulse.0;
+        self.knockback.1 += impulse.1;
+        self.invulnerable = self.invulnerability;
+        true
+    }
+    // Counts down invulnerability and slides the object along its knockback, which bleeds off
+    // over a fraction of a second
+    fn tick_knockback(&mut self, dt: f64, obstacles: &[Rect]) -> &mut Self {
+        self.invulnerable = (self.invulnerable - dt).max(0.0);
+
+        let (vx, vy) = self.knockback;
+        if vx == 0.0 && vy == 0.0 {
+            return self;
+        }
+
+        self.move_by(vx * dt, vy * dt, obstacles);
+
+        let decay = (-KNOCKBACK_DECAY * dt).exp();
+        self.knockback = (vx * decay, vy * decay);
+        if self.knockback.0.abs() < 1.0 && self.knockback.1.abs() < 1.0 {
+            self.knockback = (0.0, 0.0);
+        }
+        self
+    }
+    // Accelerates along `direction` (a unit vector, or zero to coast to a stop under friction), up
+    // to `speed` scaled by any speed buff. Hitting a wall kills the velocity into it.
+    fn drive(&mut self, direction: (f64, f64), dt: f64, obstacles: &[Rect]) -> &mut Self {
+        let boost = self.multiplier(Stat::Speed);
+        let max_speed = self.speed * boost * self.statuses.movement();
+        let (mut vx, mut vy) = self.velocity;
+        let direction = if self.statuses.stunned() { (0.0, 0.0) } else { direction };
+
+        if direction != (0.0, 0.0) {
+            vx += direction.0 * self.acceleration * boost * dt;
+            vy += direction.1 * self.acceleration * boost * dt;
+        } else {
+            let current = (vx.powf(2.0) + vy.powf(2.0)).sqrt();
+            let slowed = (current - self.friction * dt).max(0.0);
+            if current > 0.0 {
+                vx *= slowed / current;
+                vy *= slowed / current;
+            }
         }
+
+        let current = (vx.powf(2.0) + vy.powf(2.0)).sqrt();
+        if current > max_speed {
+            vx *= max_speed / current;
+            vy *= max_speed / current;
+        }
+
+        let (x, y) = (self.x, self.y);
+        self.move_by(vx * dt, vy * dt, obstacles);
+        if (self.x - x - vx * dt).abs() > 1e-9 {
+            vx = 0.0;
+        }
+        if (self.y - y - vy * dt).abs() > 1e-9 {
+            vy = 0.0;
+        }
+
+        self.velocity = (vx, vy);
+        self
     }
-    fn up(&mut self, amount: f64) -> &mut Self {
-        self.y -= amount;
+    fn afflict(&mut self, effects: &[StatusEffect]) -> &mut Self {
+        for effect in effects.iter() {
+            self.statuses.apply(*effect);
+        }
         self
     }
-    fn down(&mut self, amount: f64) -> &mut Self {
-        self.y += amount;
+    // Damage over time ignores invulnerability and doesn't knock anything back
+    fn tick_statuses(&mut self, dt: f64) -> &mut Self {
+        self.hp -= self.statuses.tick(dt);
         self
     }
-    fn left(&mut self, amount: f64) -> &mut Self {
-        self.x -= amount;
+    // Picking up a buff for a stat that's already boosted replaces it rather than stacking
+    fn add_buff(&mut self, buff: Buff) -> &mut Self {
+        self.buffs.retain(|existing| existing.stat != buff.stat);
+        self.buffs.push(buff);
         self
     }
-    fn right(&mut self, amount: f64) -> &mut Self {
-        self.x += amount;
+    fn multiplier(&self, stat: Stat) -> f64 {
+        self.buffs.iter()
+            .filter(|buff| buff.stat == stat)
+            .map(|buff| buff.multiplier)
+            .product()
+    }
+    fn tick_buffs(&mut self, dt: f64) -> &mut Self {
+        for buff in self.buffs.iter_mut() {
+            buff.remaining -= dt;
+        }
+        self.buffs.retain(|buff| buff.remaining > 0.0);
         self
     }
+    fn bounds(&self) -> Rect {
+        Rect {
+            x: self.x - self.size / 2.0,
+            y: self.y - self.size / 2.0,
+            w: self.size,
+            h: self.size,
+        }
+    }
+    // The colour the body is drawn in: yellow while an enemy winds up an attack, tinted by any
+    // status effects
+    pub fn draw_color(&self) -> [u8; 3] {
+        let winding_up = self.telegraph.is_some()
+            || self.boss.as_ref().is_some_and(|boss| boss.windup.is_some());
+
+        let mut color = match self.name {
+            ObjectName::Enemy(_) if winding_up => [255,200,0],
+            _ => self.color,
+        };
+        // status effects tint the body halfway towards their colour
+        if let Some(tint) = self.statuses.tint() {
+            for (channel, tint) in color.iter_mut().zip(tint) {
+                *channel = ((*channel as u16 + tint as u16) / 2) as u8;
+            }
+        }
+        color
+    }
     fn as_overlay(&self, buffer_height: usize, buffer_width: usize) -> Overlay {
         // TODO: match on self and write different rendering functions for different objects given
         // their position and state
         let mut overlay = Overlay::new(buffer_height, buffer_width);
 
-        use ObjectName::*;
+        // blink while invulnerable
+        if (self.invulnerable * 10.0) as i64 % 2 == 1 {
+            return overlay;
+        }
 
         //println!("Drawing at {}, {}", self.x, self.y);
 
+        let color = self.draw_color();
+
         for x in ((self.x-self.size/2.0) as usize)..((self.x+self.size/2.0) as usize) {
             for y in ((self.y-self.size/2.0) as usize)..((self.y+self.size/2.0) as usize) {
-                let _ = overlay.set_pixel(
-                    x,
-                    y,
-                    match self.name {
-                        Player =>   &[0,0,0],
-                        Enemy(_) => &[255,30,30],
-                        _ =>        &[150,150,150],
-                    }
-                );
+                let _ = overlay.set_pixel(x, y, &color);
             }
         }
 
+        if self.boss.is_some() {
+            self.draw_boss_parts(&mut overlay);
+        }
+
         overlay
     }
-    fn collides_with(&self, object: Object) -> bool {
-        let margin_self = self.size / 2.0;
-        let margin_other = object.size / 2.0;
-        
-        for player_corner in 
-        [
-            (self.x-margin_self, self.y-margin_self),
-            (self.x+margin_self, self.y-margin_self),
-            (self.x-margin_self, self.y+margin_self),
-            (self.x+margin_self, self.y+margin_self)
-        ].iter() {
-            if
-                player_corner.0 > object.x-margin_other &&
-                player_corner.1 > object.y-margin_other &&
-                player_corner.0 < object.x+margin_other &&
-                player_corner.1 < object.y+margin_other
-
-            {
-                return true;
+    // A darker core and a pair of eyes on top of the body, with the core growing brighter as the
+    // boss moves through its phases
+    fn draw_boss_parts(&self, overlay: &mut Overlay) {
+        let phase = self.boss.as_ref().map_or(0, |boss| boss.phase) as f64;
+        let core = [
+            (self.color[0] as f64 * (0.4 + phase * 0.2)).min(255.0) as u8,
+            (self.color[1] as f64 * 0.4) as u8,
+            (self.color[2] as f64 * 0.4) as u8,
+        ];
+        let quarter = self.size / 4.0;
+
+        for x in ((self.x-quarter) as usize)..((self.x+quarter) as usize) {
+            for y in ((self.y-quarter/2.0) as usize)..((self.y+quarter*1.5) as usize) {
+                let _ = overlay.set_pixel(x, y, &core);
+            }
+        }
+
+        let eye = (self.size / 10.0).max(2.0);
+        for eye_x in [self.x - quarter, self.x + quarter - eye] {
+            for x in (eye_x as usize)..((eye_x+eye) as usize) {
+                for y in ((self.y-quarter*1.5) as usize)..((self.y-quarter*1.5+eye) as usize) {
+                    let _ = overlay.set_pixel(x, y, &[255,255,255]);
+                }
+            }
+        }
+    }
+    fn collider(&self) -> Collider {
+        Collider::aabb(self.x, self.y, self.size / 2.0, self.size / 2.0)
+    }
+    fn contact(&self, other: &Object) -> Option<Contact> {
+        self.collider().contact(&other.collider())
+    }
+    // Fires `action` and the secondary ability, each only if it has recharged. `grid` has to
+    // index `objects`.
+    fn use_ability(&mut self, objects: &mut [Object], grid: &SpatialHash, rng: &mut impl Rng) -> &mut Self {
+        if self.statuses.stunned() {
+            return self;
+        }
+        if self.recharge <= 0.0 {
+            self.fire(&self.action, objects, grid, rng);
+            self.recharge = self.cooldown;
+        }
+        if let Some((action, cooldown)) = self.secondary.as_ref() {
+            if self.secondary_recharge <= 0.0 {
+                self.fire(action, objects, grid, rng);
+                self.secondary_recharge = *cooldown;
             }
         }
 
-        false
+        self
+    }
+    // The damage `action` deals at `magnitude` when this object uses it. Novas are always shock.
+    fn strike(&self, action: &Action, magnitude: f64) -> Damage {
+        let kind = match action {
+            Action::Projectile(_) => self.damage_type,
+            Action::Nova(_) => DamageType::Shock,
+        };
+        Damage::new(magnitude, kind).with_crit(self.crit_chance)
+    }
+    // how far `action` reaches when this object uses it
+    fn reach(&self, action: &Action) -> f64 {
+        use Action::*;
+        match action {
+            Projectile(magnitude) => (magnitude * 1.5) + 19.5f64 + self.range_bonus,
+            Nova(magnitude) => 40.0 + magnitude * 10.0 + self.range_bonus,
+        }
+    }
+    fn fire(&self, action: &Action, objects: &mut [Object], grid: &SpatialHash, rng: &mut impl Rng) {
+        use Action::*;
+
+        let range = self.reach(action);
+        let knock = |from: &Object, target: &Object| {
+            let (dx, dy) = (target.x - from.x, target.y - from.y);
+            let length = (dx.powf(2.0) + dy.powf(2.0)).sqrt().max(f64::EPSILON);
+            (dx / length * ATTACK_KNOCKBACK, dy / length * ATTACK_KNOCKBACK)
+        };
+
+        match *action {
+            Projectile(magnitude) => {
+                let magnitude = magnitude * self.multiplier(Stat::Damage);
+                let distance = |object: &Object| (self.x - object.x).powf(2.0) + (self.y - object.y).powf(2.0);
+
+                let closest = grid.query_radius(self.x, self.y, range)
+                    .into_iter()
+                    .min_by(|a, b| distance(&objects[*a]).total_cmp(&distance(&objects[*b])));
+
+                if let Some(idx) = closest {
+                    println!("Damaging enemy");
+                    let impulse = knock(self, &objects[idx]);
+                    if objects[idx].take_hit(self.strike(action, magnitude), impulse, rng) {
+                        objects[idx].afflict(&self.on_hit);
+                    }
+                }
+            },
+            Nova(magnitude) => {
+                let magnitude = magnitude * self.multiplier(Stat::Damage);
+                for idx in grid.query_radius(self.x, self.y, range) {
+                    let impulse = knock(self, &objects[idx]);
+                    if objects[idx].take_hit(self.strike(action, magnitude), impulse, rng) {
+                        objects[idx].afflict(&self.on_hit);
+                    }
+                }
+            },
+        }
+    }
+    // Moves towards (or away from) `target` according to the object's behaviour
+    fn steer_towards(&mut self, target: &Object, dt: f64, obstacles: &[Rect]) -> &mut Self {
+        let (dx, dy) = (target.x - self.x, target.y - self.y);
+        let distance = (dx.powf(2.0) + dy.powf(2.0)).sqrt();
+        if distance <= f64::EPSILON {
+            return self;
+        }
+
+        let direction = match self.behaviour {
+            Behaviour::Player => 0.0,
+            Behaviour::Chase => 1.0,
+            Behaviour::Kite => {
+                let preferred = self.fire_range * 0.75;
+                if distance > preferred {
+                    1.0
+                } else if distance < preferred * 0.6 {
+                    -1.0
+                } else {
+                    0.0
+                }
+            },
+        };
+
+        let step = self.speed * self.statuses.movement() * direction * dt;
+        self.move_by(dx / distance * step, dy / distance * step, obstacles)
+    }
+    // Counts down both ability cooldowns
+    fn tick_recharge(&mut self, dt: f64) -> &mut Self {
+        self.recharge = (self.recharge - dt).max(0.0);
+        self.secondary_recharge = (self.secondary_recharge - dt).max(0.0);
+        self
     }
-    fn use_ability(&mut self, objects: &mut [Object]) -> &mut Self {
+    // Counts down the enemy's cooldown and wind-up, returning a projectile aimed at `target` once
+    // the wind-up finishes. The wind-up is what gets telegraphed on screen.
+    fn tick_action(&mut self, dt: f64, target: &mut Object, rng: &mut impl Rng) -> Option<Projectile> {
+        self.tick_recharge(dt);
 
-        let mut closest = &mut Object::new(ObjectName::Enemy(EnemyType::Weak), 10000.0, 10000.0, 0.0, 10000.0, Action::Projectile(0.0));
-        let mut mag = 0.0;
+        if !matches!(self.name, ObjectName::Enemy(_)) || self.statuses.stunned() {
+            return None;
+        }
 
-        for object in objects.iter_mut() {
-            use Action::*;
+        if let Some(windup) = self.telegraph.as_mut() {
+            *windup -= dt;
+            if *windup > 0.0 {
+                return None;
+            }
+            self.telegraph = None;
+            self.recharge = self.cooldown;
 
-            #[allow(clippy::single_match)]
-            match self.action {
+            use Action::*;
+            return match self.action {
                 Projectile(magnitude) => {
-                    let current_distance =  ((self.x - object.x).powf(2.0) + (self.y - object.y).powf(2.0)).sqrt();
-                    let closest_distance = ((self.x - closest.x).powf(2.0) + (self.y - object.y).powf(2.0)).sqrt();
-                        if current_distance <= (magnitude * 1.5) + 19.5f64 && current_distance < closest_distance {
-                            println!("Found closer enemy");
-                            closest = &mut *object;
-                            mag = magnitude;
+                    let damage = self.strike(&self.action, magnitude);
+                    let mut shot = projectile::Projectile::aimed((self.x, self.y), (target.x, target.y), damage);
+                    shot.effects = self.on_hit.clone();
+                    Some(shot)
+                },
+                Nova(magnitude) => {
+                    let (dx, dy) = (target.x - self.x, target.y - self.y);
+                    let distance = (dx.powf(2.0) + dy.powf(2.0)).sqrt();
+                    if distance <= self.reach(&self.action) {
+                        let length = distance.max(f64::EPSILON);
+                        let damage = self.strike(&self.action, magnitude);
+                        if target.take_hit(damage, (dx / length * ATTACK_KNOCKBACK, dy / length * ATTACK_KNOCKBACK), rng) {
+                            target.afflict(&self.on_hit);
                         }
-                    },
-                    _ => {},
-            }
+                    }
+                    None
+                },
+            };
         }
-        
-        println!("Damaging enemy");
-        closest.hp -= mag;
-        
-        self
+
+        let distance = ((self.x - target.x).powf(2.0) + (self.y - target.y).powf(2.0)).sqrt();
+        if self.recharge <= 0.0 && distance <= self.fire_range {
+            self.telegraph = Some(projectile::TELEGRAPH_TIME);
+        }
+
+        None
     }
 
 }
 
 mod anim_data;
-use anim_data::{Overlay, Alter};
+use anim_data::Overlay;
+
+mod archetype;
+use archetype::{Archetype, Archetypes, Behaviour};
+
+mod boss;
+use boss::BossState;
+
+mod args;
+
+mod boundary;
+
+mod difficulty;
+use difficulty::Difficulty;
+
+mod collider;
+use collider::{Collider, Contact};
+
+mod projectile;
+use projectile::Projectile;
+
+mod spatial;
+use spatial::SpatialHash;
+
+mod wave;
+
+mod input;
+use input::{Direction, Press};
+
+mod level;
+use level::{list_levels, Rect};
+
+mod arena;
+
+mod pickup;
+use pickup::{Buff, PickupTable, Stat};
+
+mod coop;
+use coop::Coop;
+
+mod damage;
+use damage::{Damage, DamageType, Defence, Hit};
+
+mod status;
+use status::{StatusEffect, Statuses};
+
+mod upgrade;
+
+mod score;
+use score::{HighScore, HighScores};
+
+mod state;
+use state::{GameState, MenuItem};
+
+mod rng;
+
+mod font;
+
+mod popup;
+use popup::DamagePopup;
+
+mod achievements;
+use achievements::Achievements;
+
+mod world;
+use world::{World, WorldEvent, Outcome};
+
+mod save;
+
+mod protocol;
+
+mod server;
+
+mod client;
+use client::Client;
 
 pub fn black_background() -> RgbImage {
     let mut bg = RgbImage::new(WIDTH as u32, HEIGHT as u32);
@@ -225,9 +704,11 @@ enum WhichData {
 enum ChannelEvent {
     Input(tgui::event::Event),
     Frame(String),
+    Announce(String),
+    Hud(String),
+    Upgrades(Vec<String>),
     Data(WhichData),
     Ready,
-    Done
 }
 
 use ChannelEvent::*;
@@ -237,8 +718,24 @@ use WhichData::*;
 pub const WIDTH: usize = 500;
 pub const HEIGHT: usize = 500;
 
+// knockback speeds in pixels per second, and how fast knockback dies off
+pub const CONTACT_KNOCKBACK: f64 = 250.0;
+pub const PROJECTILE_KNOCKBACK: f64 = 120.0;
+pub const ATTACK_KNOCKBACK: f64 = 150.0;
+pub const KNOCKBACK_DECAY: f64 = 8.0;
+
 fn main() {
 
+    // `--server [addr]` runs the game headless for networked clients, without any UI
+    if args::present(&["--server"]) {
+        let addr = args::value(&["--server"]).unwrap_or_else(|| server::DEFAULT_ADDR.to_string());
+        let level = arena::from_args().unwrap_or_else(|| list_levels().remove(0));
+        if let Err(e) = server::run(&addr, level, Archetypes::load(), PickupTable::load(), Difficulty::from_args()) {
+            println!("Server stopped: {e}");
+        }
+        return;
+    }
+
     let tgui = Arc::new(TGui::new());
     let t_gui = tgui.clone();
     let _tgui = t_gui.clone();
@@ -251,6 +748,9 @@ fn main() {
     let image = ui.image_view(Some(&image_frame));
     image.set_image_string(&render_string(white_background()));
     let _image = Arc::new(image);
+
+    let hud = ui.text_view(Some(&layout), "");
+    let announcement = ui.text_view(Some(&layout), "");
     
     let controls_section = ui.linear_layout(Some(&layout), true);
     let top_controls = ui.linear_layout(Some(&controls_section), false);
@@ -277,7 +777,46 @@ fn main() {
     let down_right_arrow = ui.button(Some(&bottom_controls), "┘");
     let _ = ui.space(Some(&bottom_controls));
 
+    // the second player's pad, only used in co-op
+    let partner_controls = ui.linear_layout(Some(&controls_section), false);
+    let _ = ui.text_view(Some(&partner_controls), "P2");
+    let partner_left = ui.button(Some(&partner_controls), "<");
+    let partner_up = ui.button(Some(&partner_controls), "^");
+    let partner_down = ui.button(Some(&partner_controls), "v");
+    let partner_right = ui.button(Some(&partner_controls), ">");
+    let partner_action = ui.button(Some(&partner_controls), "o");
+    partner_action.set_background_color(Color::from_rgb(0, 90, 255));
+
+    let upgrade_row = ui.linear_layout(Some(&layout), false);
+    let upgrade_buttons: Vec<_> = (0..upgrade::CHOICES)
+        .map(|_| ui.button(Some(&upgrade_row), "-"))
+        .collect();
+
+    // each button's directions, and whether it's on the second player's pad
+    let direction_buttons = {
+        use Direction::*;
+        [
+            (up_left_arrow, vec![Up, Left], false),
+            (up_arrow, vec![Up], false),
+            (up_right_arrow, vec![Up, Right], false),
+            (left_arrow, vec![Left], false),
+            (right_arrow, vec![Right], false),
+            (down_left_arrow, vec![Down, Left], false),
+            (down_arrow, vec![Down], false),
+            (down_right_arrow, vec![Down, Right], false),
+            (partner_left, vec![Left], true),
+            (partner_up, vec![Up], true),
+            (partner_down, vec![Down], true),
+            (partner_right, vec![Right], true),
+        ]
+    };
+    // buttons only report being pressed and let go once asked to; otherwise a press is one click
+    for (button, _, _) in direction_buttons.iter() {
+        button.send_touch_event(true);
+    }
+
     let bottom_line = ui.linear_layout(Some(&layout), false);
+    let pause_button = ui.button(Some(&bottom_line), "PAUSE");
     let exit_button = ui.button(Some(&bottom_line), "EXIT");
     exit_button.set_background_color(Color::from_rgb(255, 30, 30));
 
@@ -295,104 +834,196 @@ fn main() {
     });
 
 
-    let mut player = Object::new(
-        ObjectName::Player,
-        (WIDTH/2) as f64,
-        (HEIGHT/2) as f64,
-        10.0,
-        20.0,
-        Action::Projectile(2.0),
-    );
-
-    let mut enemy_list = Vec::with_capacity(10);
-
-    for i in 9..40 {
-        enemy_list.push( Object::new(
-            ObjectName::Enemy(EnemyType::Medium),
-            ((WIDTH/2 + (i+1) * 10) % WIDTH) as f64,
-            ((HEIGHT/2 + (i+1) * 10) % HEIGHT) as f64,
-            10.0,
-            5.0,
-            Action::Projectile(0.5)
-        ));
+    let archetypes = Archetypes::load();
+    let pickup_table = PickupTable::load();
+    let mut levels = list_levels();
+    // a generated arena goes first, so it's the one on the title screen
+    if let Some(level) = arena::from_args() {
+        levels.retain(|existing| existing.name != level.name);
+        levels.insert(0, level);
     }
 
-    let enemy_list = Arc::new(Mutex::new(enemy_list));
+    let level_select = ui.linear_layout(Some(&layout), false);
+    let level_buttons: Vec<_> = levels.iter()
+        .map(|level| ui.button(Some(&level_select), &level.name))
+        .collect();
+
+    let mut current_level = 0;
+    let mut difficulty = Difficulty::from_args();
+    let mut coop = Coop::from_args();
+    let world = World::from_level(&levels[current_level], &archetypes, pickup_table.clone(), difficulty, coop);
+    announcement.set_text(&world.screen_text());
+    let world = Arc::new(Mutex::new(world));
+
+    // `--connect <addr>` plays on a server instead of the local world, which then just sits on
+    // the title screen
+    let remote = args::value(&["--connect"]).and_then(|addr| {
+        match Client::connect(&addr, "player") {
+            Ok(client) => Some(client),
+            Err(e) => {
+                println!("Warning: couldn't join {addr}: {e}");
+                None
+            },
+        }
+    });
+    if remote.is_some() {
+        announcement.set_text("");
+    }
 
-    let player = Arc::new(Mutex::new(player.clone()));
+    // shared so the main thread can write out progress when a level is left early
+    let achievements = Arc::new(Mutex::new(Achievements::load()));
 
-    let _player = player.clone();
-    let _enemy_list = enemy_list.clone();
+    let _world = world.clone();
+    let _remote = remote.clone();
+    let _achievements = achievements.clone();
     rayon::spawn(move || {
-        // TODO: make the player a mutex as well as the game state and send image over a channel to
-        // handle rendering
+        let mut last_tick = Instant::now();
+        let mut last_hud = String::new();
+        let mut last_upgrades = Vec::new();
+        // off the playing screen nothing moves, so one frame per screen is enough
+        let mut drawn_state = None;
+        // unlocks waiting for their turn on screen, and the one showing with its seconds left
+        let mut toasts: Vec<String> = Vec::new();
+        let mut toast: Option<(String, f64)> = None;
+        let mut drawn_toast = None;
+        // damage numbers still floating
+        let mut popups: Vec<DamagePopup> = Vec::new();
         loop {
             if let Ok(ev) = imagerqrx.recv() {
                 println!("Rendering thread received event");
-                let mut player = loop {
-                    if let Ok(lock) = _player.lock() {
+                if let Some(client) = _remote.as_ref() {
+                    if ev == ChannelEvent::Ready {
+                        let dt = last_tick.elapsed().as_secs_f64().min(0.1);
+                        last_tick = Instant::now();
+                        for text in client.take_messages() {
+                            let _ = _eventtx.send(ChannelEvent::Announce(text));
+                        }
+                        let Some(snapshot) = client.snapshot() else {
+                            continue;
+                        };
+                        if snapshot.hud != last_hud {
+                            let _ = _eventtx.send(ChannelEvent::Hud(snapshot.hud.clone()));
+                            last_hud = snapshot.hud.clone();
+                        }
+                        if snapshot.upgrades != last_upgrades {
+                            let _ = _eventtx.send(ChannelEvent::Upgrades(snapshot.upgrades.clone()));
+                            last_upgrades = snapshot.upgrades.clone();
+                        }
+                        popups.extend(client.take_hits());
+                        popups.retain_mut(|popup| popup.tick(dt));
+                        let mut frame = snapshot.render(&client.level);
+                        for popup in popups.iter() {
+                            popup.draw(&mut frame);
+                        }
+                        let mut base64 = render_string(frame);
+                        base64.shrink_to_fit();
+                        let _ = _eventtx.send(ChannelEvent::Frame(base64));
+                    }
+                    continue;
+                }
+                let mut world = loop {
+                    if let Ok(lock) = _world.lock() {
                         break lock;
                     }
                 };
-                let mut enemy_list = loop {
-                    if let Ok(lock) = _enemy_list.lock() {
+                let mut achievements = loop {
+                    if let Ok(lock) = _achievements.lock() {
                         break lock;
                     }
                 };
                 if ev == ChannelEvent::Ready {
-                    let mut background = white_background();
-
-                    let player_overlay = player.as_overlay(WIDTH, HEIGHT);
-
-                    background.overlay(0,0,1.0,1.0, &player_overlay);
-
-                    for (i, pix) in player_overlay.pixels.iter().enumerate() {
-                        if let Some(val) = pix {
-                            //println!("first valid value is {:?}, idx {i}", val);
+                    // cap the step so a stalled frame doesn't teleport everything
+                    let dt = last_tick.elapsed().as_secs_f64().min(0.1);
+                    last_tick = Instant::now();
+
+                    let events = world.tick(dt);
+                    // HP lost this tick has to be seen before a wave it cleared is judged flawless
+                    achievements.watch(&world);
+                    for event in events {
+                        toasts.extend(achievements.observe(&event, &world));
+                        match event {
+                            WorldEvent::Announce(text) => {
+                                println!("{text}");
+                                let _ = _eventtx.send(ChannelEvent::Announce(text));
+                            },
+                            WorldEvent::LevelUp(choices) => {
+                                let _ = _eventtx.send(ChannelEvent::Announce(world.screen_text()));
+                                let _ = _eventtx.send(ChannelEvent::Upgrades(choices));
+                            },
+                            WorldEvent::Finished(outcome) => {
+                                let mut high_scores = HighScores::load();
+                                let position = high_scores.add(HighScore {
+                                    score: world.score.total,
+                                    level: world.name.clone(),
+                                    date: score::today(),
+                                    difficulty: world.difficulty,
+                                });
+                                high_scores.save();
+
+                                let headline = match outcome {
+                                    Outcome::Won => format!("{} complete!", world.name),
+                                    Outcome::Lost => "Game over.".to_string(),
+                                };
+                                let placed = match position {
+                                    Some(idx) => format!(" New high score, #{}!", idx + 1),
+                                    None => String::new(),
+                                };
+                                world.summary = format!(
+                                    "{headline} Score {}.{placed}\nKills {}   Time {:.0}s   Lv {}   Achievements {}/{}\n\nHigh scores ({}):\n{}",
+                                    world.score.total,
+                                    world.score.kills,
+                                    world.elapsed,
+                                    world.experience.level,
+                                    achievements.progress.unlocked.len(),
+                                    achievements.list.len(),
+                                    world.difficulty.name(),
+                                    high_scores.table(world.difficulty),
+                                );
+                                println!("{}", world.summary);
+                                let text = world.screen_text();
+                                let _ = _eventtx.send(ChannelEvent::Announce(text));
+                            },
+                            WorldEvent::Damaged { player, x, y, hit } => {
+                                popups.push(DamagePopup::new(player, x, y, hit));
+                            },
+                            WorldEvent::Killed(_)
+                            | WorldEvent::WaveStarted(_)
+                            | WorldEvent::WaveCleared(_) => {},
                         }
                     }
-                    let mut cached_delete = Vec::new();
-                    for (i, enemy) in enemy_list.iter_mut().enumerate() {
-                        let distance_to_player = ((player.x-enemy.x).powf(2.0) + (player.y-enemy.y).powf(2.0)).sqrt();
-                        if player.collides_with(enemy.clone())  {
-                            player.hp -= match enemy.name {
-                                ObjectName::Enemy(EnemyType::Weak) => 0.5f64,
-                                ObjectName::Enemy(EnemyType::Medium) => 1.0f64,
-                                ObjectName::Enemy(EnemyType::Strong) => 1.5f64,
-                                _ => 0.0,
-                            };
-                        }
-
-                        if player.hp <= 0.0 {
-                            println!("Game over.");
-                            let _ = _eventtx.send(ChannelEvent::Done);
-                        }
-
-                        let distance_to_player = distance_to_player * if let ObjectName::Enemy(enemy_type) = &enemy.name {
-                            use EnemyType::*;
-                            match enemy_type {
-                                Weak => 1.8,
-                                Medium => 1.5,
-                                Strong => 1.2,
-                                _ => 1.0,
-                            }
-                        } else { 1.0 };
+                    // damage numbers hang still while the game does
+                    if world.state == GameState::Playing && world.upgrade_choices.is_none() {
+                        popups.retain_mut(|popup| popup.tick(dt));
+                    }
 
-                        enemy.x += (player.x - enemy.x) / distance_to_player;
-                        enemy.y += (player.y - enemy.y) / distance_to_player;
+                    toast = match toast.take() {
+                        Some((name, remaining)) if remaining > dt => Some((name, remaining - dt)),
+                        _ if !toasts.is_empty() => Some((toasts.remove(0), achievements::TOAST_TIME)),
+                        _ => None,
+                    };
+                    let showing = toast.as_ref().map(|(name, _)| name.clone());
 
-                        if enemy.hp <= 0.0 {
-                            cached_delete.push(i);
-                        } else {
-                            background.overlay(0,0,1.0,1.0, &enemy.as_overlay(WIDTH, HEIGHT));
-                        }
+                    // a toast coming or going needs a fresh frame even on a still screen
+                    if world.state != GameState::Playing && drawn_state == Some(world.state) && drawn_toast == showing {
+                        continue;
                     }
+                    drawn_state = Some(world.state);
+                    drawn_toast = showing;
 
-                    for idx in cached_delete.iter().rev() {
-                        let _ = enemy_list.remove(*idx);
+                    let hud = world.hud();
+                    if hud != last_hud {
+                        let _ = _eventtx.send(ChannelEvent::Hud(hud.clone()));
+                        last_hud = hud;
                     }
 
-                    let mut base64 = render_string(background);
+                    let mut frame = world.render();
+                    for popup in popups.iter() {
+                        popup.draw(&mut frame);
+                    }
+                    if let Some((name, _)) = toast.as_ref() {
+                        achievements::draw_toast(&mut frame, name);
+                    }
+                    let mut base64 = render_string(frame);
                     base64.shrink_to_fit();
 
                     let _ = _eventtx.send(ChannelEvent::Frame(base64));
@@ -403,6 +1034,29 @@ fn main() {
     });
 
     
+    // kills since the last unlock are only in memory, so leaving a level writes them out
+    let save_achievements = || {
+        let achievements = loop {
+            if let Ok(lock) = achievements.lock() {
+                break lock;
+            }
+        };
+        achievements.save();
+    };
+    // a new world starts its own flawless-wave watch rather than carrying on the last one's
+    let reset_achievements = |world: &World| {
+        let mut achievements = loop {
+            if let Ok(lock) = achievements.lock() {
+                break lock;
+            }
+        };
+        achievements.reset(world);
+    };
+
+    // set by the first touch down or up from a direction button. After that their clicks only
+    // repeat the touch, so they're ignored.
+    let mut touch_reported = false;
+
     let mut running = true;
     while running {
         if let Ok(event) = eventrx.try_recv() {
@@ -410,86 +1064,210 @@ fn main() {
             match event {
                 Input(event) => {
                     println!("Got Input event {:?}", event.value);
-                    if event.id == exit_button.get_id() {
-                        running = false;
-                    }
-                    if event.id == action_button.get_id() {
-                        let mut player = loop {
-                            if let Ok(lock) = player.lock() {
+                    let press = match Press::from_event_value(&event.value) {
+                        Some(Press::Tap) if touch_reported => None,
+                        Some(press) => {
+                            touch_reported |= press != Press::Tap;
+                            Some(press)
+                        },
+                        None => None,
+                    };
+                    if input::is_backgrounded(&event.value) {
+                        let mut world = loop {
+                            if let Ok(lock) = world.lock() {
                                 break lock;
                             }
                         };
-                        let mut enemy_list = loop {
-                            if let Ok(lock) = enemy_list.lock() {
-                                break lock;
-                            }
-                        };
-                        player.use_ability(&mut enemy_list);
+                        if world.transition(GameState::Paused) {
+                            println!("Paused while in the background");
+                            announcement.set_text(&world.screen_text());
+                        }
                     }
-                    if event.id == up_left_arrow.get_id() {
-                        let mut player = loop {
-                            if let Ok(lock) = player.lock() {
-                                break lock;
-                            }
-                        };
-                        player.up(10.0).left(10.0);
+                    if event.id == exit_button.get_id() {
+                        running = false;
+                        save_achievements();
+                        if let Some(client) = remote.as_ref() {
+                            client.leave();
+                        }
                     }
-
-                    if event.id == up_arrow.get_id() {
-                        let mut player = loop {
-                            if let Ok(lock) = player.lock() {
-                                break lock;
+                    // playing on a server, only the first pad and the upgrades do anything
+                    if let Some(client) = remote.as_ref() {
+                        if event.id == action_button.get_id() {
+                            client.attack();
+                        }
+                        if let Some(idx) = upgrade_buttons.iter().position(|button| event.id == button.get_id()) {
+                            client.choose(idx);
+                        }
+                        let pressed = direction_buttons.iter().find(|(button, _, _)| event.id == button.get_id()).zip(press);
+                        if let Some(((_, directions, false), press)) = pressed {
+                            for direction in directions.iter() {
+                                client.input(*direction, press);
                             }
-                        };
-                        player.up(10.0);
+                        }
+                        continue;
                     }
-                    if event.id == up_right_arrow.get_id() {
-                        let mut player = loop {
-                            if let Ok(lock) = player.lock() {
+                    if let Some(idx) = level_buttons.iter().position(|button| event.id == button.get_id()) {
+                        let mut world = loop {
+                            if let Ok(lock) = world.lock() {
                                 break lock;
                             }
                         };
-                        player.up(10.0).right(10.0);
+                        current_level = idx;
+                        save_achievements();
+                        *world = World::from_level(&levels[idx], &archetypes, pickup_table.clone(), difficulty, coop);
+                        reset_achievements(&world);
+                        world.transition(GameState::Playing);
+                        announcement.set_text(&levels[idx].name);
+                        for button in upgrade_buttons.iter() {
+                            button.set_text("-");
+                        }
                     }
-                    if event.id == down_arrow.get_id() {
-                        let mut player = loop {
-                            if let Ok(lock) = player.lock() {
+                    if event.id == pause_button.get_id() {
+                        let mut world = loop {
+                            if let Ok(lock) = world.lock() {
                                 break lock;
                             }
                         };
-                        player.down(10.0);
+                        let to = match world.state {
+                            GameState::Paused => GameState::Playing,
+                            _ => GameState::Paused,
+                        };
+                        if world.transition(to) {
+                            announcement.set_text(&world.screen_text());
+                        }
                     }
-                    if event.id == down_left_arrow.get_id() {
-                        let mut player = loop {
-                            if let Ok(lock) = player.lock() {
+                    if let Some(idx) = upgrade_buttons.iter().position(|button| event.id == button.get_id()) {
+                        let mut world = loop {
+                            if let Ok(lock) = world.lock() {
                                 break lock;
                             }
                         };
-                        player.down(10.0).left(10.0);
+                        if world.state == GameState::Playing && world.choose_upgrade(idx) {
+                            for button in upgrade_buttons.iter() {
+                                button.set_text("-");
+                            }
+                            announcement.set_text("");
+                        }
                     }
-                    if event.id == down_right_arrow.get_id() {
-                        let mut player = loop {
-                            if let Ok(lock) = player.lock() {
+                    if event.id == action_button.get_id() {
+                        let mut world = loop {
+                            if let Ok(lock) = world.lock() {
                                 break lock;
                             }
                         };
-                        player.down(10.0).right(10.0);
+                        if world.state == GameState::Playing {
+                            world.player_attack(false);
+                        } else {
+                            // `o` picks the highlighted menu item
+                            match world.menu.selected(world.state) {
+                                Some(MenuItem::Start) | Some(MenuItem::Resume) => {
+                                    world.transition(GameState::Playing);
+                                },
+                                Some(MenuItem::Restart) => {
+                                    save_achievements();
+                                    *world = World::from_level(&levels[current_level], &archetypes, pickup_table.clone(), difficulty, coop);
+                                    reset_achievements(&world);
+                                    world.transition(GameState::Playing);
+                                },
+                                Some(MenuItem::Continue) => {
+                                    match save::load() {
+                                        Ok(loaded) => {
+                                            // Restart and the title screen carry on with the saved run's settings
+                                            difficulty = loaded.difficulty;
+                                            coop = loaded.coop;
+                                            match levels.iter().position(|level| level.name == loaded.name) {
+                                                Some(idx) => current_level = idx,
+                                                // its file has gone or been renamed since, so Restart
+                                                // uses the copy in the save
+                                                None => {
+                                                    levels.push(loaded.level.clone());
+                                                    current_level = levels.len() - 1;
+                                                },
+                                            }
+                                            *world = loaded;
+                                            reset_achievements(&world);
+                                            world.state = GameState::Paused;
+                                            println!("Loaded {}", save::SAVE_PATH);
+                                        },
+                                        Err(e) => println!("Warning: couldn't load {} ({e})", save::SAVE_PATH),
+                                    }
+                                },
+                                Some(MenuItem::Save) => {
+                                    match save::save(&world) {
+                                        Ok(()) => println!("Saved to {}", save::SAVE_PATH),
+                                        Err(e) => println!("Warning: couldn't save to {} ({e})", save::SAVE_PATH),
+                                    }
+                                },
+                                Some(MenuItem::Difficulty) => {
+                                    difficulty = difficulty.next();
+                                    let menu = world.menu.clone();
+                                    *world = World::from_level(&levels[current_level], &archetypes, pickup_table.clone(), difficulty, coop);
+                                    reset_achievements(&world);
+                                    world.menu = menu;
+                                    println!("Difficulty: {}", difficulty.name());
+                                },
+                                Some(MenuItem::Players) => {
+                                    coop = coop.next();
+                                    let menu = world.menu.clone();
+                                    *world = World::from_level(&levels[current_level], &archetypes, pickup_table.clone(), difficulty, coop);
+                                    reset_achievements(&world);
+                                    world.menu = menu;
+                                    println!("Co-op: {}", coop.name());
+                                },
+                                Some(MenuItem::Title) => {
+                                    save_achievements();
+                                    *world = World::from_level(&levels[current_level], &archetypes, pickup_table.clone(), difficulty, coop);
+                                    reset_achievements(&world);
+                                },
+                                Some(MenuItem::Quit) => {
+                                    save_achievements();
+                                    running = false;
+                                },
+                                None => {},
+                            }
+                            // label the upgrade buttons from whatever world the menu left, which
+                            // for a loaded save may have a level-up waiting
+                            let labels: Vec<&str> = world.upgrade_choices.iter()
+                                .flatten()
+                                .map(|upgrade| upgrade.label())
+                                .collect();
+                            for (idx, button) in upgrade_buttons.iter().enumerate() {
+                                button.set_text(labels.get(idx).copied().unwrap_or("-"));
+                            }
+                            announcement.set_text(&world.screen_text());
+                        }
                     }
-                    if event.id == left_arrow.get_id() {
-                        let mut player = loop {
-                            if let Ok(lock) = player.lock() {
+                    if event.id == partner_action.get_id() {
+                        let mut world = loop {
+                            if let Ok(lock) = world.lock() {
                                 break lock;
                             }
                         };
-                        player.left(10.0);
+                        world.player_attack(true);
                     }
-                    if event.id == right_arrow.get_id() {
-                        let mut player = loop {
-                            if let Ok(lock) = player.lock() {
+                    let pressed = direction_buttons.iter().find(|(button, _, _)| event.id == button.get_id()).zip(press);
+                    if let Some(((_, directions, partner), press)) = pressed {
+                        let mut world = loop {
+                            if let Ok(lock) = world.lock() {
                                 break lock;
                             }
                         };
-                        player.right(10.0);
+                        if world.state == GameState::Playing {
+                            let input = if *partner { &mut world.partner_input } else { &mut world.input };
+                            for direction in directions.iter() {
+                                input.apply(*direction, press);
+                            }
+                        } else if press != Press::Up {
+                            // up and down move the menu highlight
+                            let step = match directions.first() {
+                                Some(Direction::Up) => -1,
+                                Some(Direction::Down) => 1,
+                                _ => 0,
+                            };
+                            let state = world.state;
+                            world.menu.move_by(step, state);
+                            announcement.set_text(&world.screen_text());
+                        }
                     }
 
                 },
@@ -497,10 +1275,21 @@ fn main() {
                     image.set_image_string(&string);
                     continue;
                 },
-                Done => {
-                    running = false;
+                Announce(text) => {
+                    announcement.set_text(&text);
                     continue;
-                }
+                },
+                Hud(text) => {
+                    hud.set_text(&text);
+                    continue;
+                },
+                Upgrades(labels) => {
+                    // no labels clears the buttons
+                    for (idx, button) in upgrade_buttons.iter().enumerate() {
+                        button.set_text(labels.get(idx).map(|label| label.as_str()).unwrap_or("-"));
+                    }
+                    continue;
+                },
                 _ => {},
             }
         }
@@ -0,0 +1,153 @@
+use rand::Rng;
+
+use serde::{Deserialize, Serialize};
+
+use std::fs::read_to_string;
+
+use crate::{
+    anim_data::Overlay,
+    collider::Collider,
+    Object,
+};
+
+pub const PICKUP_PATH: &str = "data/pickups.json";
+
+// the shipped drop table (bombs, boosts and health with their odds and how long drops last),
+// baked in for when data/pickups.json won't load
+const BUILTIN_PICKUPS: &str = include_str!("../data/pickups.json");
+
+pub const PICKUP_SIZE: f64 = 7.0;
+// pickups blink for this many seconds before they despawn
+pub const PICKUP_WARNING: f64 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PickupKind {
+    Health { amount: f64 },
+    DamageBoost { multiplier: f64, duration: f64 },
+    SpeedBoost { multiplier: f64, duration: f64 },
+    // kills every enemy and clears every projectile on screen
+    Bomb,
+}
+
+impl PickupKind {
+    fn color(&self) -> [u8; 3] {
+        use PickupKind::*;
+        match self {
+            Health { .. } => [40,200,40],
+            DamageBoost { .. } => [230,120,0],
+            SpeedBoost { .. } => [0,170,230],
+            Bomb => [120,0,160],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Stat {
+    Damage,
+    Speed,
+}
+
+// A timed multiplier on one of an object's stats
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Buff {
+    pub stat: Stat,
+    pub multiplier: f64,
+    pub remaining: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Drop {
+    // 0 to 1, rolled separately for each kill
+    pub chance: f64,
+    #[serde(flatten)]
+    pub kind: PickupKind,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PickupTable {
+    // seconds a pickup stays on the ground
+    pub lifetime: f64,
+    pub drops: Vec<Drop>,
+}
+
+impl PickupTable {
+    // Reads `PICKUP_PATH`, falling back to the copy compiled into the binary if it can't be read
+    // or parsed.
+    pub fn load() -> Self {
+        let loaded = read_to_string(PICKUP_PATH)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()));
+
+        match loaded {
+            Ok(table) => table,
+            Err(e) => {
+                println!("Warning: couldn't load {PICKUP_PATH} ({e}), using built-in pickups");
+                serde_json::from_str(BUILTIN_PICKUPS).unwrap()
+            },
+        }
+    }
+    // Drops are rolled in file order and the first one that hits is used, so a kill drops at
+    // most one pickup
+    pub fn roll(&self, rng: &mut impl Rng, x: f64, y: f64) -> Option<Pickup> {
+        self.drops.iter()
+            .find(|drop| rng.gen_bool(drop.chance.clamp(0.0, 1.0)))
+            .map(|drop| Pickup {
+                kind: drop.kind,
+                x,
+                y,
+                ttl: self.lifetime,
+            })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pickup {
+    pub kind: PickupKind,
+    pub x: f64,
+    pub y: f64,
+    pub ttl: f64,
+}
+
+impl Pickup {
+    pub fn collider(&self) -> Collider {
+        Collider::aabb(self.x, self.y, PICKUP_SIZE / 2.0, PICKUP_SIZE / 2.0)
+    }
+    pub fn touches(&self, object: &Object) -> bool {
+        self.collider().overlaps(&object.collider())
+    }
+    pub fn as_overlay(&self, buffer_height: usize, buffer_width: usize) -> Overlay {
+        let mut overlay = Overlay::new(buffer_height, buffer_width);
+
+        if self.ttl < PICKUP_WARNING && (self.ttl * 8.0) as i64 % 2 == 1 {
+            return overlay;
+        }
+
+        let half = PICKUP_SIZE / 2.0;
+        for x in ((self.x-half) as usize)..((self.x+half) as usize) {
+            for y in ((self.y-half) as usize)..((self.y+half) as usize) {
+                let _ = overlay.set_pixel(x, y, &self.kind.color());
+            }
+        }
+
+        overlay
+    }
+}
+
+// Applies a collected pickup to `player`. Bombs are handled by the world, since they hit
+// everything else.
+pub fn apply(kind: PickupKind, player: &mut Object) {
+    use PickupKind::*;
+    match kind {
+        Health { amount } => {
+            player.hp = (player.hp + amount).min(player.max_hp);
+        },
+        DamageBoost { multiplier, duration } => {
+            player.add_buff(Buff { stat: Stat::Damage, multiplier, remaining: duration });
+        },
+        SpeedBoost { multiplier, duration } => {
+            player.add_buff(Buff { stat: Stat::Speed, multiplier, remaining: duration });
+        },
+        Bomb => {},
+    }
+}
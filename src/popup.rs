@@ -0,0 +1,59 @@
+use image::RgbImage;
+
+use crate::{
+    damage::{
+        DamageType,
+        Hit,
+    },
+    font,
+};
+
+// seconds a damage number stays up
+pub const POPUP_TIME: f64 = 0.8;
+// pixels per second it drifts upwards
+const RISE_SPEED: f64 = 30.0;
+
+// What a hit came to, floating up from where it landed
+#[derive(Debug, Clone, PartialEq)]
+pub struct DamagePopup {
+    pub x: f64,
+    pub y: f64,
+    // whether it was a player that got hit, which is drawn in red whatever the type
+    pub player: bool,
+    pub hit: Hit,
+    // seconds left on screen
+    pub remaining: f64,
+}
+
+impl DamagePopup {
+    pub fn new(player: bool, x: f64, y: f64, hit: Hit) -> Self {
+        DamagePopup { x, y, player, hit, remaining: POPUP_TIME }
+    }
+    // Drifts up and counts down, returning false once it's due to go
+    pub fn tick(&mut self, dt: f64) -> bool {
+        self.y -= RISE_SPEED * dt;
+        self.remaining -= dt;
+        self.remaining > 0.0
+    }
+    // e.g. "2.5 FIRE", with a "!" and double size for a crit
+    pub fn text(&self) -> String {
+        let crit = if self.hit.crit { "!" } else { "" };
+        format!("{:.1} {}{crit}", self.hit.amount, self.hit.kind.name())
+    }
+    pub fn draw(&self, image: &mut RgbImage) {
+        let scale = if self.hit.crit { 2 } else { 1 };
+        let color = match (self.player, self.hit.kind) {
+            (true, _) => [230,30,30],
+            (false, DamageType::Kinetic) => [40,40,40],
+            (false, DamageType::Fire) => [255,120,0],
+            (false, DamageType::Ice) => [60,160,255],
+            (false, DamageType::Shock) => [200,170,0],
+        };
+
+        let text = self.text();
+        let left = self.x - font::text_width(&text, scale) as f64 / 2.0;
+        let top = self.y - (font::GLYPH_HEIGHT * scale) as f64 - 6.0;
+        // anything hanging off the left or top gets nudged back on rather than wrapping round
+        font::draw_text(image, &text, left.max(0.0) as u32, top.max(0.0) as u32, scale, color);
+    }
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    anim_data::Overlay,
+    collider::Collider,
+    damage::Damage,
+    status::StatusEffect,
+    Object,
+};
+
+// how long an enemy flashes before its shot leaves, in seconds
+pub const TELEGRAPH_TIME: f64 = 0.4;
+// pixels per second
+pub const PROJECTILE_SPEED: f64 = 140.0;
+pub const PROJECTILE_LIFETIME: f64 = 3.0;
+pub const PROJECTILE_SIZE: f64 = 4.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Projectile {
+    pub x: f64,
+    pub y: f64,
+    pub vx: f64,
+    pub vy: f64,
+    pub size: f64,
+    pub damage: Damage,
+    pub ttl: f64,
+    // inflicted on whatever it hits
+    pub effects: Vec<StatusEffect>,
+}
+
+impl Projectile {
+    // Fires from `from` in a straight line through `to`. The aim is fixed when the shot leaves,
+    // so moving during the telegraph dodges it.
+    pub fn aimed(from: (f64, f64), to: (f64, f64), damage: Damage) -> Self {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let distance = (dx.powf(2.0) + dy.powf(2.0)).sqrt().max(f64::EPSILON);
+
+        Projectile {
+            x: from.0,
+            y: from.1,
+            vx: dx / distance * PROJECTILE_SPEED,
+            vy: dy / distance * PROJECTILE_SPEED,
+            size: PROJECTILE_SIZE,
+            damage,
+            ttl: PROJECTILE_LIFETIME,
+            effects: Vec::new(),
+        }
+    }
+    pub fn advance(&mut self, dt: f64) -> &mut Self {
+        self.x += self.vx * dt;
+        self.y += self.vy * dt;
+        self.ttl -= dt;
+        self
+    }
+    pub fn expired(&self, width: usize, height: usize) -> bool {
+        self.ttl <= 0.0
+            || self.x < 0.0
+            || self.y < 0.0
+            || self.x >= width as f64
+            || self.y >= height as f64
+    }
+    pub fn collider(&self) -> Collider {
+        Collider::circle(self.x, self.y, self.size / 2.0)
+    }
+    pub fn hits(&self, object: &Object) -> bool {
+        self.collider().overlaps(&object.collider())
+    }
+    pub fn as_overlay(&self, buffer_height: usize, buffer_width: usize) -> Overlay {
+        let mut overlay = Overlay::new(buffer_height, buffer_width);
+
+        for x in ((self.x-self.size/2.0) as usize)..((self.x+self.size/2.0) as usize) {
+            for y in ((self.y-self.size/2.0) as usize)..((self.y+self.size/2.0) as usize) {
+                let _ = overlay.set_pixel(x, y, &[255,120,0]);
+            }
+        }
+
+        overlay
+    }
+}
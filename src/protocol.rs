@@ -0,0 +1,216 @@
+// The wire format between `server` and `client`: one JSON object per line, in both directions.
+// Clients send what the player pressed, the server answers with a snapshot of the world every
+// tick.
+
+use image::RgbImage;
+
+use serde::{
+    de::DeserializeOwned,
+    Deserialize,
+    Serialize,
+};
+
+use std::io::{
+    self,
+    BufRead,
+    Write,
+};
+
+use crate::{
+    anim_data::Alter,
+    boundary::Boundary,
+    damage::Hit,
+    input::{
+        Direction,
+        Press,
+    },
+    level::{
+        Hazard,
+        Level,
+        Rect,
+    },
+    world::World,
+    Object,
+};
+
+// bumped whenever a message changes shape, so old clients are turned away instead of misreading
+pub const PROTOCOL_VERSION: u32 = 4;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    // the first thing a client sends
+    Join { name: String, version: u32 },
+    Input { direction: Direction, press: Press },
+    Attack,
+    // picks one of the snapshot's `upgrades`
+    Choose { upgrade: usize },
+    Leave,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    // the answer to a join that got a player slot
+    Welcome { id: u32, level: LevelInfo },
+    // the answer to a join that didn't, after which the server hangs up
+    Rejected { reason: String },
+    Joined { id: u32, name: String },
+    Left { id: u32, name: String },
+    Announce { text: String },
+    // a hit landed, for drawing its damage number
+    Damaged { player: bool, x: f64, y: f64, hit: Hit },
+    Snapshot(Snapshot),
+}
+
+// The parts of a level that never change, sent once on joining
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelInfo {
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    pub background: [u8; 3],
+    pub obstacles: Vec<Rect>,
+    pub exit: Option<Rect>,
+    pub hazards: Vec<Hazard>,
+    // the storm's size depends on time, so it's drawn from each snapshot's `elapsed`
+    pub boundary: Boundary,
+}
+
+impl LevelInfo {
+    pub fn new(level: &Level) -> Self {
+        LevelInfo {
+            name: level.name.clone(),
+            width: level.width,
+            height: level.height,
+            background: level.background,
+            obstacles: level.obstacles.clone(),
+            exit: level.exit,
+            hazards: level.hazards.clone(),
+            boundary: level.boundary.clone(),
+        }
+    }
+}
+
+// Just enough of an object to draw it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Body {
+    // stays the same across snapshots, so a client can tell which body is which
+    pub id: u64,
+    pub x: f64,
+    pub y: f64,
+    pub size: f64,
+    // as drawn, so wind-ups and status effects show
+    pub color: [u8; 3],
+}
+
+impl Body {
+    fn new(object: &Object) -> Self {
+        Body { id: object.id, x: object.x, y: object.y, size: object.size, color: object.draw_color() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub tick: u64,
+    // seconds into the level, which sets how far the storm has closed
+    pub elapsed: f64,
+    pub players: Vec<Body>,
+    pub enemies: Vec<Body>,
+    pub projectiles: Vec<[f64; 2]>,
+    pub hud: String,
+    // labels of the upgrades waiting to be picked, empty when there's no level-up pending
+    pub upgrades: Vec<String>,
+}
+
+impl Snapshot {
+    pub fn new(world: &World, tick: u64) -> Self {
+        Snapshot {
+            tick,
+            elapsed: world.elapsed,
+            players: std::iter::once(&world.player)
+                .chain(world.partner.iter())
+                .filter(|player| player.hp > 0.0)
+                .map(Body::new)
+                .collect(),
+            enemies: world.enemies.iter().map(Body::new).collect(),
+            projectiles: world.projectiles.iter().map(|projectile| [projectile.x, projectile.y]).collect(),
+            hud: world.hud(),
+            upgrades: world.upgrade_choices.iter()
+                .flatten()
+                .map(|upgrade| upgrade.label().to_string())
+                .collect(),
+        }
+    }
+    // The snapshot `alpha` of the way from this one to `next`. Bodies are matched by id; anything
+    // only in `next` appears where it is there, and projectiles aren't blended at all.
+    pub fn lerp(&self, next: &Snapshot, alpha: f64) -> Snapshot {
+        let blend = |from: &[Body], to: &[Body]| -> Vec<Body> {
+            to.iter().map(|body| {
+                match from.iter().find(|old| old.id == body.id) {
+                    Some(old) => Body {
+                        x: old.x + (body.x - old.x) * alpha,
+                        y: old.y + (body.y - old.y) * alpha,
+                        ..*body
+                    },
+                    None => *body,
+                }
+            }).collect()
+        };
+
+        Snapshot {
+            elapsed: self.elapsed + (next.elapsed - self.elapsed) * alpha,
+            players: blend(&self.players, &next.players),
+            enemies: blend(&self.enemies, &next.enemies),
+            ..next.clone()
+        }
+    }
+    pub fn render(&self, level: &LevelInfo) -> RgbImage {
+        let (width, height) = (level.width, level.height);
+        let mut image = RgbImage::from_pixel(width as u32, height as u32, level.background.into());
+
+        for obstacle in level.obstacles.iter() {
+            image.overlay(0,0,1.0,1.0, &obstacle.as_overlay(width, height, &[90,90,90]));
+        }
+        if let Some(exit) = level.exit {
+            image.overlay(0,0,1.0,1.0, &exit.as_overlay(width, height, &[60,200,60]));
+        }
+        for hazard in level.hazards.iter() {
+            image.overlay(0,0,1.0,1.0, &hazard.area.as_overlay(width, height, &Hazard::COLOR));
+        }
+
+        level.boundary.draw_storm(&mut image, self.elapsed);
+
+        for body in self.players.iter().chain(self.enemies.iter()) {
+            let half = body.size / 2.0;
+            for (x, y) in level.boundary.draw_positions(body.x, body.y, half, width, height) {
+                let rect = Rect { x: x - half, y: y - half, w: body.size, h: body.size };
+                image.overlay(0,0,1.0,1.0, &rect.as_overlay(width, height, &body.color));
+            }
+        }
+        for [x, y] in self.projectiles.iter() {
+            for (x, y) in level.boundary.draw_positions(*x, *y, 2.0, width, height) {
+                let rect = Rect { x: x - 2.0, y: y - 2.0, w: 4.0, h: 4.0 };
+                image.overlay(0,0,1.0,1.0, &rect.as_overlay(width, height, &[255,120,0]));
+            }
+        }
+
+        image
+    }
+}
+
+pub fn send<T: Serialize>(stream: &mut impl Write, message: &T) -> io::Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    stream.flush()
+}
+
+// The next message, or None once the other end has hung up
+pub fn receive<T: DeserializeOwned>(reader: &mut impl BufRead) -> io::Result<Option<T>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&line)?))
+}
@@ -0,0 +1,48 @@
+use rand::{
+    thread_rng,
+    Rng,
+    RngCore,
+};
+
+use serde::{Deserialize, Serialize};
+
+// The world's own random numbers, for wave makeup, spawn points, drops, upgrade offers, crits and
+// enemies' first shots. Its whole state is one number, so it goes into save files and a resumed
+// game rolls exactly what it would have rolled had it never stopped.
+// (SplitMix64: tiny, fast and plenty random for a game.)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameRng {
+    state: u64,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng { state: seed }
+    }
+    pub fn from_entropy() -> Self {
+        GameRng::new(thread_rng().gen())
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+// A game in progress, written out from the pause menu and picked up again from the title screen.
+// The save holds the whole `World`, including the (already difficulty-scaled) archetypes and the
+// pickup table it was playing with and the state of its random numbers, so editing the data
+// files doesn't change a saved run.
+
+use serde::{Deserialize, Serialize};
+
+use std::fs::{
+    read_to_string,
+    write,
+};
+
+use crate::world::World;
+
+pub const SAVE_PATH: &str = "savegame.json";
+// bumped whenever anything saved changes shape, so an old save is refused instead of half-read.
+// 2: the world has hazards.
+// 3: the world keeps a copy of its level.
+pub const SAVE_VERSION: u32 = 3;
+
+#[derive(Serialize)]
+struct SaveFile<'a> {
+    version: u32,
+    world: &'a World,
+}
+
+#[derive(Deserialize)]
+struct LoadFile {
+    world: World,
+}
+
+pub fn save(world: &World) -> Result<(), String> {
+    let file = SaveFile { version: SAVE_VERSION, world };
+    let json = serde_json::to_string(&file).map_err(|e| e.to_string())?;
+    write(SAVE_PATH, json).map_err(|e| e.to_string())
+}
+
+// Reads the save back, checking its version before anything else
+pub fn load() -> Result<World, String> {
+    let json = read_to_string(SAVE_PATH).map_err(|e| e.to_string())?;
+    let value: serde_json::Value = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+
+    match value.get("version").and_then(|version| version.as_u64()) {
+        Some(version) if version == SAVE_VERSION as u64 => {},
+        Some(version) => {
+            return Err(format!("the save is version {version}, this build reads version {SAVE_VERSION}"));
+        },
+        None => return Err("the save has no version".to_string()),
+    }
+
+    let mut world = serde_json::from_value::<LoadFile>(value).map_err(|e| e.to_string())?.world;
+    world.grid.rebuild(&world.enemies);
+    Ok(world)
+}
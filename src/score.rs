@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+
+use crate::difficulty::Difficulty;
+
+use std::{
+    fs::{
+        read_to_string,
+        write,
+    },
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+pub const HIGH_SCORE_PATH: &str = "highscores.json";
+pub const HIGH_SCORE_COUNT: usize = 10;
+
+// seconds a combo survives without a kill before it starts to drop
+pub const COMBO_WINDOW: f64 = 3.0;
+// once it starts dropping, one step is lost this often
+pub const COMBO_DECAY: f64 = 0.5;
+pub const COMBO_STEP: f64 = 0.25;
+pub const MAX_MULTIPLIER: f64 = 4.0;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Score {
+    pub total: u64,
+    pub kills: u64,
+    // kills strung together without letting the timer run out
+    pub combo: u32,
+    pub combo_timer: f64,
+}
+
+impl Score {
+    pub fn multiplier(&self) -> f64 {
+        (1.0 + self.combo as f64 * COMBO_STEP).min(MAX_MULTIPLIER)
+    }
+    // Scores a kill worth `points` at the current multiplier, then extends the combo
+    pub fn kill(&mut self, points: u64) -> &mut Self {
+        self.total += (points as f64 * self.multiplier()).round() as u64;
+        self.kills += 1;
+        self.combo += 1;
+        self.combo_timer = COMBO_WINDOW;
+        self
+    }
+    pub fn tick(&mut self, dt: f64) -> &mut Self {
+        if self.combo == 0 {
+            return self;
+        }
+
+        self.combo_timer -= dt;
+        if self.combo_timer <= 0.0 {
+            self.combo -= 1;
+            self.combo_timer = COMBO_DECAY;
+        }
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HighScore {
+    pub score: u64,
+    pub level: String,
+    // YYYY-MM-DD, UTC
+    pub date: String,
+    // scores from before difficulties existed were all on normal
+    #[serde(default)]
+    pub difficulty: Difficulty,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    pub entries: Vec<HighScore>,
+}
+
+impl HighScores {
+    // A missing or unreadable file is just an empty table
+    pub fn load() -> Self {
+        read_to_string(HIGH_SCORE_PATH)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+    pub fn save(&self) {
+        match serde_json::to_string_pretty(self) {
+            Ok(json) => {
+                if let Err(e) = write(HIGH_SCORE_PATH, json) {
+                    println!("Warning: couldn't save {HIGH_SCORE_PATH} ({e})");
+                }
+            },
+            Err(e) => println!("Warning: couldn't serialize high scores ({e})"),
+        }
+    }
+    // Each difficulty keeps its own table, so scores are only ranked against runs on the same
+    // settings
+    fn ranked(&self, difficulty: Difficulty) -> impl Iterator<Item = &HighScore> {
+        self.entries.iter().filter(move |entry| entry.difficulty == difficulty)
+    }
+    // Inserts `entry` in order and drops whatever falls off the bottom of its difficulty's table.
+    // Returns the entry's position in that table, if it made it.
+    pub fn add(&mut self, entry: HighScore) -> Option<usize> {
+        let difficulty = entry.difficulty;
+        let position = self.ranked(difficulty).position(|existing| entry.score > existing.score)
+            .unwrap_or(self.ranked(difficulty).count());
+        if position >= HIGH_SCORE_COUNT {
+            return None;
+        }
+
+        let idx = self.entries.iter().position(|existing| entry.score > existing.score)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(idx, entry);
+
+        let mut kept = 0;
+        self.entries.retain(|existing| {
+            if existing.difficulty != difficulty {
+                return true;
+            }
+            kept += 1;
+            kept <= HIGH_SCORE_COUNT
+        });
+        Some(position)
+    }
+    pub fn table(&self, difficulty: Difficulty) -> String {
+        self.ranked(difficulty)
+            .enumerate()
+            .map(|(i, entry)| format!("{}. {} - {} ({})", i + 1, entry.score, entry.level, entry.date))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+pub fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+// Days since 1970-01-01 to a proleptic Gregorian date, after Howard Hinnant's `civil_from_days`
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
@@ -0,0 +1,396 @@
+// Headless multiplayer. The server owns the only real `World`: it runs the simulation at a fixed
+// rate, applies whatever its clients send, and broadcasts a snapshot after every tick. Up to two
+// clients play, as the first and second player of a co-op game.
+
+use std::{
+    io::{
+        self,
+        BufReader,
+    },
+    net::{
+        Shutdown,
+        TcpListener,
+        TcpStream,
+    },
+    sync::mpsc::{
+        channel,
+        Sender,
+    },
+    thread::{
+        self,
+        sleep,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use crate::{
+    archetype::Archetypes,
+    difficulty::Difficulty,
+    coop::Coop,
+    level::Level,
+    pickup::PickupTable,
+    protocol::{
+        self,
+        ClientMessage,
+        LevelInfo,
+        ServerMessage,
+        Snapshot,
+        PROTOCOL_VERSION,
+    },
+    state::GameState,
+    world::{
+        Outcome,
+        World,
+        WorldEvent,
+    },
+};
+
+pub const DEFAULT_ADDR: &str = "0.0.0.0:7878";
+pub const TICK_RATE: f64 = 30.0;
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Slot {
+    Player,
+    Partner,
+}
+
+// What the connection threads hand to the simulation loop
+enum Incoming {
+    Connected(u32, TcpStream),
+    Message(u32, ClientMessage),
+    Disconnected(u32),
+}
+
+struct Peer {
+    id: u32,
+    name: String,
+    stream: TcpStream,
+    // None until the client has joined
+    slot: Option<Slot>,
+}
+
+pub fn run(addr: &str, level: Level, archetypes: Archetypes, pickup_table: PickupTable, difficulty: Difficulty) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    println!("Serving \"{}\" on {}", level.name, listener.local_addr()?);
+    serve(listener, level, archetypes, pickup_table, difficulty)
+}
+
+// Runs the server on an already bound listener, which lets it be started on a free port. Never
+// returns unless the listener fails.
+pub fn serve(listener: TcpListener, level: Level, archetypes: Archetypes, pickup_table: PickupTable, difficulty: Difficulty) -> io::Result<()> {
+    let (tx, rx) = channel::<Incoming>();
+    thread::spawn(move || accept(listener, tx));
+
+    let info = LevelInfo::new(&level);
+    let new_world = || World::from_level(&level, &archetypes, pickup_table.clone(), difficulty, Coop::Off);
+    let mut world = new_world();
+    let mut peers: Vec<Peer> = Vec::new();
+    let mut tick = 0u64;
+    let step = Duration::from_secs_f64(1.0 / TICK_RATE);
+
+    loop {
+        let started = Instant::now();
+
+        // peers that hung up or couldn't be written to, dropped once this tick's done
+        let mut gone = Vec::new();
+        while let Ok(incoming) = rx.try_recv() {
+            match incoming {
+                Incoming::Connected(id, stream) => {
+                    peers.push(Peer { id, name: String::new(), stream, slot: None });
+                },
+                Incoming::Disconnected(id) => gone.push(id),
+                Incoming::Message(id, message) => {
+                    let slot = match peers.iter().find(|peer| peer.id == id) {
+                        Some(peer) => peer.slot,
+                        None => continue,
+                    };
+                    match (message, slot) {
+                        (ClientMessage::Join { name, version }, None) => {
+                            let taken: Vec<Slot> = peers.iter().filter_map(|peer| peer.slot).collect();
+                            let free = [Slot::Player, Slot::Partner].into_iter().find(|slot| !taken.contains(slot));
+                            let peer = peers.iter_mut().find(|peer| peer.id == id).unwrap();
+                            let reason = if version != PROTOCOL_VERSION {
+                                Some(format!("server speaks protocol {PROTOCOL_VERSION}, client speaks {version}"))
+                            } else if free.is_none() {
+                                Some("server is full".to_string())
+                            } else {
+                                None
+                            };
+                            if let Some(reason) = reason {
+                                println!("Turned away {name}: {reason}");
+                                let _ = protocol::send(&mut peer.stream, &ServerMessage::Rejected { reason });
+                                let _ = peer.stream.shutdown(Shutdown::Both);
+                                gone.push(id);
+                                continue;
+                            }
+
+                            peer.name = name.clone();
+                            peer.slot = free;
+                            if protocol::send(&mut peer.stream, &ServerMessage::Welcome { id, level: info.clone() }).is_err() {
+                                gone.push(id);
+                                continue;
+                            }
+                            match free {
+                                Some(Slot::Partner) => world.add_partner(),
+                                _ => { world.transition(GameState::Playing); },
+                            }
+                            println!("{name} joined");
+                            broadcast(&mut peers, &ServerMessage::Joined { id, name }, &mut gone);
+                        },
+                        (ClientMessage::Input { direction, press }, Some(slot)) => {
+                            let input = match slot {
+                                Slot::Player => &mut world.input,
+                                Slot::Partner => &mut world.partner_input,
+                            };
+                            input.apply(direction, press);
+                        },
+                        (ClientMessage::Attack, Some(slot)) => world.player_attack(slot == Slot::Partner),
+                        (ClientMessage::Choose { upgrade }, Some(_)) => { world.choose_upgrade(upgrade); },
+                        (ClientMessage::Leave, _) => gone.push(id),
+                        // anything sent before joining, or a second join
+                        _ => {},
+                    }
+                },
+            }
+        }
+
+        if world.state == GameState::Playing {
+            for event in world.tick(step.as_secs_f64()) {
+                match event {
+                    WorldEvent::Announce(text) => {
+                        broadcast(&mut peers, &ServerMessage::Announce { text }, &mut gone);
+                    },
+                    WorldEvent::Finished(outcome) => {
+                        // no title screen to go back to, so the level starts over straight away
+                        let headline = match outcome {
+                            Outcome::Won => format!("{} complete!", world.name),
+                            Outcome::Lost => "Game over.".to_string(),
+                        };
+                        let text = format!("{headline} Score {}. Starting over.", world.score.total);
+                        println!("{text}");
+                        broadcast(&mut peers, &ServerMessage::Announce { text }, &mut gone);
+                        world = new_world();
+                        world.transition(GameState::Playing);
+                        if peers.iter().any(|peer| peer.slot == Some(Slot::Partner)) {
+                            world.add_partner();
+                        }
+                    },
+                    WorldEvent::Damaged { player, x, y, hit } => {
+                        broadcast(&mut peers, &ServerMessage::Damaged { player, x, y, hit }, &mut gone);
+                    },
+                    WorldEvent::LevelUp(_)
+                    | WorldEvent::Killed(_)
+                    | WorldEvent::WaveStarted(_)
+                    | WorldEvent::WaveCleared(_) => {},
+                }
+            }
+        }
+
+        tick += 1;
+        let snapshot = ServerMessage::Snapshot(Snapshot::new(&world, tick));
+        broadcast(&mut peers, &snapshot, &mut gone);
+
+        gone.sort();
+        gone.dedup();
+        for id in gone {
+            let Some(idx) = peers.iter().position(|peer| peer.id == id) else {
+                continue;
+            };
+            let peer = peers.remove(idx);
+            let _ = peer.stream.shutdown(Shutdown::Both);
+            match peer.slot {
+                Some(Slot::Player) => {
+                    // whoever was second carries on as the first player
+                    if let Some(partner) = peers.iter_mut().find(|peer| peer.slot == Some(Slot::Partner)) {
+                        partner.slot = Some(Slot::Player);
+                        world.promote_partner();
+                    }
+                },
+                Some(Slot::Partner) => world.remove_partner(),
+                None => continue,
+            }
+            println!("{} left", peer.name);
+            let mut ignored = Vec::new();
+            broadcast(&mut peers, &ServerMessage::Left { id, name: peer.name }, &mut ignored);
+        }
+        if world.state != GameState::Title && !peers.iter().any(|peer| peer.slot.is_some()) {
+            println!("Everyone left, resetting");
+            world = new_world();
+        }
+
+        if let Some(rest) = step.checked_sub(started.elapsed()) {
+            sleep(rest);
+        }
+    }
+}
+
+// Hands every new connection to the simulation loop, with a thread reading its messages
+fn accept(listener: TcpListener, tx: Sender<Incoming>) {
+    let mut next_id = 1;
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                println!("Warning: failed to accept a connection: {e}");
+                continue;
+            },
+        };
+        let _ = stream.set_nodelay(true);
+        // a client that stops reading gets dropped rather than stalling everyone else
+        let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+        let id = next_id;
+        next_id += 1;
+
+        let reader = match stream.try_clone() {
+            Ok(reader) => reader,
+            Err(e) => {
+                println!("Warning: failed to accept a connection: {e}");
+                continue;
+            },
+        };
+        if tx.send(Incoming::Connected(id, stream)).is_err() {
+            return;
+        }
+
+        let tx = tx.clone();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            loop {
+                match protocol::receive::<ClientMessage>(&mut reader) {
+                    Ok(Some(message)) => {
+                        if tx.send(Incoming::Message(id, message)).is_err() {
+                            return;
+                        }
+                    },
+                    Ok(None) => break,
+                    Err(e) => {
+                        println!("Dropping client {id}: {e}");
+                        break;
+                    },
+                }
+            }
+            let _ = tx.send(Incoming::Disconnected(id));
+        });
+    }
+}
+
+// Sends `message` to every joined peer, noting the ones it couldn't reach in `gone`
+fn broadcast(peers: &mut [Peer], message: &ServerMessage, gone: &mut Vec<u32>) {
+    for peer in peers.iter_mut().filter(|peer| peer.slot.is_some()) {
+        if protocol::send(&mut peer.stream, message).is_err() {
+            gone.push(peer.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::{
+        client::Client,
+        input::{
+            Direction,
+            Press,
+        },
+    };
+
+    // A server on a free local port, running for the rest of the test process
+    fn start() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || serve(listener, Level::builtin(), Archetypes::load(), PickupTable::load(), Difficulty::Normal));
+        addr
+    }
+
+    // Polls `check` for a few seconds, for things that take a tick or two to come through
+    fn eventually(check: impl Fn() -> bool) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if check() {
+                return true;
+            }
+            sleep(Duration::from_millis(20));
+        }
+        false
+    }
+
+    // Keeps trying to join until there's a free slot, for after a leave the server may not have
+    // handled yet
+    fn join_when_free(addr: &str, name: &str) -> Arc<Client> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            match Client::connect(addr, name) {
+                Ok(client) => return client,
+                Err(e) if Instant::now() < deadline && e.to_string() == "server is full" => {
+                    sleep(Duration::from_millis(20));
+                },
+                Err(e) => panic!("{name} couldn't join: {e}"),
+            }
+        }
+    }
+
+    fn players(client: &Client) -> usize {
+        client.snapshot_at(Instant::now()).map_or(0, |snapshot| snapshot.players.len())
+    }
+
+    #[test]
+    fn two_clients_play_and_a_third_is_turned_away() {
+        let addr = start();
+        let first = Client::connect(&addr, "first").unwrap();
+        let second = Client::connect(&addr, "second").unwrap();
+
+        assert_eq!(first.level.name, Level::builtin().name);
+        assert!(eventually(|| players(&first) == 2), "first client never saw both players");
+        assert!(eventually(|| players(&second) == 2), "second client never saw both players");
+
+        let tick = |client: &Client| client.snapshot_at(Instant::now()).unwrap().tick;
+        let before = tick(&first);
+        assert!(eventually(|| tick(&first) > before), "snapshots stopped coming");
+
+        let third = Client::connect(&addr, "third");
+        let error = third.err().expect("a third client was let in");
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionRefused);
+        assert_eq!(error.to_string(), "server is full");
+        // and the two already in carry on
+        assert!(eventually(|| players(&first) == 2 && players(&second) == 2));
+    }
+
+    #[test]
+    fn input_moves_the_player() {
+        let addr = start();
+        let client = Client::connect(&addr, "mover").unwrap();
+        let x = || client.snapshot_at(Instant::now()).map(|snapshot| snapshot.players[0].x);
+
+        assert!(eventually(|| x().is_some()));
+        let start_x = x().unwrap();
+        client.input(Direction::Right, Press::Down);
+        assert!(eventually(|| x().unwrap() > start_x + 5.0), "the player didn't move");
+    }
+
+    #[test]
+    fn leaving_frees_the_slot_for_someone_else() {
+        let addr = start();
+        let first = Client::connect(&addr, "first").unwrap();
+        let second = Client::connect(&addr, "second").unwrap();
+        assert!(eventually(|| players(&second) == 2));
+
+        // the second player carries on alone as the first
+        first.leave();
+        assert!(eventually(|| players(&second) == 1), "the leaver's player stayed in");
+        assert!(eventually(|| second.take_messages().iter().any(|message| message == "first left")));
+
+        let rejoined = join_when_free(&addr, "first again");
+        assert!(eventually(|| players(&second) == 2 && players(&rejoined) == 2));
+
+        // with everyone gone the server takes new players from scratch, once it's seen them go
+        second.leave();
+        rejoined.leave();
+        let fresh = join_when_free(&addr, "fresh");
+        assert!(eventually(|| players(&fresh) == 1));
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use crate::Object;
+
+pub const CELL_SIZE: f64 = 32.0;
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    idx: usize,
+    x: f64,
+    y: f64,
+}
+
+// Uniform grid over object indices. Each object is filed under every cell its bounds touch, so
+// a query only has to look at the cells its own area covers. Indices refer to whatever slice was
+// passed to `rebuild`, and go stale as soon as that slice is reordered.
+#[derive(Debug, Clone)]
+pub struct SpatialHash {
+    cell_size: f64,
+    cells: HashMap<(i64, i64), Vec<Entry>>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f64) -> Self {
+        SpatialHash {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+    fn cell(&self, x: f64, y: f64) -> (i64, i64) {
+        ((x / self.cell_size).floor() as i64, (y / self.cell_size).floor() as i64)
+    }
+    pub fn clear(&mut self) {
+        // keep the allocations around, the same cells tend to be used again next tick
+        for entries in self.cells.values_mut() {
+            entries.clear();
+        }
+    }
+    pub fn insert(&mut self, idx: usize, object: &Object) {
+        let half = object.size / 2.0;
+        let (min_x, min_y) = self.cell(object.x - half, object.y - half);
+        let (max_x, max_y) = self.cell(object.x + half, object.y + half);
+
+        for cx in min_x..=max_x {
+            for cy in min_y..=max_y {
+                self.cells.entry((cx, cy)).or_default().push(Entry {
+                    idx,
+                    x: object.x,
+                    y: object.y,
+                });
+            }
+        }
+    }
+    pub fn rebuild(&mut self, objects: &[Object]) {
+        self.clear();
+        for (idx, object) in objects.iter().enumerate() {
+            self.insert(idx, object);
+        }
+    }
+    fn candidates(&self, min: (f64, f64), max: (f64, f64)) -> impl Iterator<Item = &Entry> {
+        let (min_x, min_y) = self.cell(min.0, min.1);
+        let (max_x, max_y) = self.cell(max.0, max.1);
+
+        (min_x..=max_x)
+            .flat_map(move |cx| (min_y..=max_y).map(move |cy| (cx, cy)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+    }
+    // Indices of every object whose bounds might overlap the box from `min` to `max`. This is a
+    // broadphase, so callers still run the exact test on what comes back.
+    pub fn query_aabb(&self, min: (f64, f64), max: (f64, f64)) -> Vec<usize> {
+        let mut found: Vec<usize> = self.candidates(min, max).map(|entry| entry.idx).collect();
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+    // Indices of every object whose centre is within `radius` of (x, y)
+    pub fn query_radius(&self, x: f64, y: f64, radius: f64) -> Vec<usize> {
+        let mut found: Vec<usize> = self.candidates((x - radius, y - radius), (x + radius, y + radius))
+            .filter(|entry| (entry.x - x).powf(2.0) + (entry.y - y).powf(2.0) <= radius.powf(2.0))
+            .map(|entry| entry.idx)
+            .collect();
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Action,
+        ObjectName,
+    };
+
+    fn object(x: f64, y: f64, size: f64) -> Object {
+        Object::new(ObjectName::Player, x, y, size, 1.0, Action::Projectile(1.0))
+    }
+
+    fn grid(objects: &[Object]) -> SpatialHash {
+        let mut grid = SpatialHash::new(CELL_SIZE);
+        grid.rebuild(objects);
+        grid
+    }
+
+    #[test]
+    fn aabb_finds_an_object_straddling_cells_from_either_side() {
+        // centred on the corner of four cells, so it's filed under all of them
+        let grid = grid(&[object(32.0, 32.0, 10.0)]);
+        assert_eq!(grid.query_aabb((0.0, 0.0), (5.0, 5.0)), vec![0]);
+        assert_eq!(grid.query_aabb((40.0, 40.0), (50.0, 50.0)), vec![0]);
+        assert_eq!(grid.query_aabb((0.0, 40.0), (5.0, 50.0)), vec![0]);
+        assert!(grid.query_aabb((70.0, 70.0), (80.0, 80.0)).is_empty());
+    }
+
+    #[test]
+    fn negative_coordinates_floor_into_the_cell_below_zero() {
+        let grid = grid(&[object(-1.0, -1.0, 0.0), object(1.0, 1.0, 0.0)]);
+        assert_eq!(grid.cell(-1.0, -1.0), (-1, -1));
+        assert_eq!(grid.cell(-32.0, 0.0), (-1, 0));
+        assert_eq!(grid.query_aabb((-5.0, -5.0), (-2.0, -2.0)), vec![0]);
+        assert_eq!(grid.query_aabb((2.0, 2.0), (5.0, 5.0)), vec![1]);
+    }
+
+    #[test]
+    fn radius_includes_the_boundary_and_nothing_past_it() {
+        let grid = grid(&[object(10.0, 0.0, 0.0), object(10.001, 0.0, 0.0), object(0.0, -10.0, 0.0)]);
+        assert_eq!(grid.query_radius(0.0, 0.0, 10.0), vec![0, 2]);
+        assert!(grid.query_radius(0.0, 0.0, 9.999).is_empty());
+    }
+
+    #[test]
+    fn an_object_in_many_cells_comes_back_once() {
+        let grid = grid(&[object(50.0, 50.0, 100.0), object(60.0, 60.0, 4.0)]);
+        assert_eq!(grid.query_aabb((0.0, 0.0), (100.0, 100.0)), vec![0, 1]);
+        assert_eq!(grid.query_radius(50.0, 50.0, 100.0), vec![0, 1]);
+    }
+}
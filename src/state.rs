@@ -0,0 +1,101 @@
+// Which screen the game is on. Only `Playing` runs the simulation; the others freeze the world
+// and turn the direction buttons into menu navigation, with `o` picking the highlighted item.
+//
+//   Title --Start--> Playing <--Resume/Pause--> Paused
+//                       |                          |
+//                    (outcome)                 Restart/Title
+//                       v
+//                    GameOver --Restart--> Playing
+//                             --Title----> Title
+//
+// Quit ends the app from any menu, and the title screen can cycle the difficulty and the number
+// of players. Save on the pause menu writes the game out, and Continue on the title screen loads
+// it back into the pause menu.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    coop::Coop,
+    difficulty::Difficulty,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GameState {
+    Title,
+    Playing,
+    Paused,
+    GameOver,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MenuItem {
+    Start,
+    Continue,
+    Difficulty,
+    Players,
+    Resume,
+    Save,
+    Restart,
+    Title,
+    Quit,
+}
+
+impl MenuItem {
+    pub fn label(&self, difficulty: Difficulty, coop: Coop) -> String {
+        use MenuItem::*;
+        match self {
+            Start => "Start".to_string(),
+            Continue => "Continue saved game".to_string(),
+            Difficulty => format!("Difficulty: {}", difficulty.name()),
+            Players => format!("Players: {}", coop.label()),
+            Resume => "Resume".to_string(),
+            Save => "Save".to_string(),
+            Restart => "Restart".to_string(),
+            Title => "Title screen".to_string(),
+            Quit => "Quit".to_string(),
+        }
+    }
+}
+
+impl GameState {
+    pub fn menu(&self) -> &'static [MenuItem] {
+        use MenuItem::*;
+        match self {
+            GameState::Title => &[Start, Continue, Difficulty, Players, Quit],
+            GameState::Playing => &[],
+            GameState::Paused => &[Resume, Save, Restart, Title, Quit],
+            GameState::GameOver => &[Restart, Title, Quit],
+        }
+    }
+}
+
+// The highlighted item on the current screen's menu
+#[derive(Debug, Clone, Default)]
+pub struct Menu {
+    pub cursor: usize,
+}
+
+impl Menu {
+    // Moves the highlight by `step` items, wrapping around the ends
+    pub fn move_by(&mut self, step: i32, state: GameState) -> &mut Self {
+        let len = state.menu().len() as i32;
+        if len > 0 {
+            self.cursor = (self.cursor as i32 + step).rem_euclid(len) as usize;
+        }
+        self
+    }
+    pub fn selected(&self, state: GameState) -> Option<MenuItem> {
+        state.menu().get(self.cursor).copied()
+    }
+    pub fn text(&self, state: GameState, difficulty: Difficulty, coop: Coop) -> String {
+        state.menu()
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let marker = if i == self.cursor { ">" } else { " " };
+                format!("{marker} {}", item.label(difficulty, coop))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+
+// seconds between damage-over-time ticks
+pub const STATUS_TICK: f64 = 0.5;
+pub const MAX_POISON_STACKS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusKind {
+    // moves at `magnitude` times normal speed
+    Slow,
+    // `magnitude` damage per second, stacking
+    Poison,
+    // can't move or act
+    Stun,
+    // `magnitude` damage per second, not stacking
+    Burn,
+}
+
+impl StatusKind {
+    pub fn tint(&self) -> [u8; 3] {
+        use StatusKind::*;
+        match self {
+            Slow => [80,140,255],
+            Poison => [90,200,60],
+            Stun => [255,255,255],
+            Burn => [255,90,20],
+        }
+    }
+    pub fn label(&self) -> &'static str {
+        use StatusKind::*;
+        match self {
+            Slow => "SLOW",
+            Poison => "PSN",
+            Stun => "STUN",
+            Burn => "BURN",
+        }
+    }
+}
+
+// What an action inflicts when it lands, as written in the archetype file
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub duration: f64,
+    #[serde(default)]
+    pub magnitude: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Status {
+    pub effect: StatusEffect,
+    pub remaining: f64,
+    // seconds until the next damage tick
+    pub next_tick: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Statuses {
+    pub active: Vec<Status>,
+}
+
+impl Statuses {
+    // Poison stacks up to `MAX_POISON_STACKS`, pushing out the one closest to wearing off. Every
+    // other kind only has one instance, which a new application refreshes to the longer
+    // duration and the stronger magnitude.
+    pub fn apply(&mut self, effect: StatusEffect) -> &mut Self {
+        let status = Status { effect, remaining: effect.duration, next_tick: STATUS_TICK };
+
+        if effect.kind == StatusKind::Poison {
+            self.active.push(status);
+            let stacks = self.active.iter().filter(|status| status.effect.kind == StatusKind::Poison).count();
+            if stacks > MAX_POISON_STACKS {
+                let weakest = self.active.iter()
+                    .enumerate()
+                    .filter(|(_, status)| status.effect.kind == StatusKind::Poison)
+                    .min_by(|(_, a), (_, b)| a.remaining.total_cmp(&b.remaining))
+                    .map(|(idx, _)| idx);
+                if let Some(idx) = weakest {
+                    self.active.remove(idx);
+                }
+            }
+            return self;
+        }
+
+        match self.active.iter_mut().find(|existing| existing.effect.kind == effect.kind) {
+            Some(existing) => {
+                existing.remaining = existing.remaining.max(effect.duration);
+                existing.effect.magnitude = match effect.kind {
+                    // a lower multiplier is the stronger slow
+                    StatusKind::Slow => existing.effect.magnitude.min(effect.magnitude),
+                    _ => existing.effect.magnitude.max(effect.magnitude),
+                };
+            },
+            None => self.active.push(status),
+        }
+        self
+    }
+    // Counts everything down and drops what has worn off. Returns the damage over time dealt in
+    // this step.
+    pub fn tick(&mut self, dt: f64) -> f64 {
+        let mut damage = 0.0;
+
+        for status in self.active.iter_mut() {
+            status.remaining -= dt;
+            if matches!(status.effect.kind, StatusKind::Poison | StatusKind::Burn) {
+                status.next_tick -= dt;
+                while status.next_tick <= 0.0 {
+                    damage += status.effect.magnitude * STATUS_TICK;
+                    status.next_tick += STATUS_TICK;
+                }
+            }
+        }
+        self.active.retain(|status| status.remaining > 0.0);
+
+        damage
+    }
+    pub fn stunned(&self) -> bool {
+        self.active.iter().any(|status| status.effect.kind == StatusKind::Stun)
+    }
+    // what movement speed gets multiplied by
+    pub fn movement(&self) -> f64 {
+        if self.stunned() {
+            return 0.0;
+        }
+        self.active.iter()
+            .filter(|status| status.effect.kind == StatusKind::Slow)
+            .map(|status| status.effect.magnitude)
+            .fold(1.0, f64::min)
+    }
+    // The colour to tint the overlay with: stun shows over burn, burn over poison, poison over slow
+    pub fn tint(&self) -> Option<[u8; 3]> {
+        use StatusKind::*;
+        [Stun, Burn, Poison, Slow].into_iter()
+            .find(|kind| self.active.iter().any(|status| status.effect.kind == *kind))
+            .map(|kind| kind.tint())
+    }
+}
@@ -0,0 +1,112 @@
+use rand::{
+    seq::SliceRandom,
+    Rng,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    status::{
+        StatusEffect,
+        StatusKind,
+    },
+    Action,
+    Object,
+};
+
+pub const CHOICES: usize = 3;
+
+// how the second ability starts out when it's picked as an upgrade
+pub const NOVA_MAGNITUDE: f64 = 2.0;
+pub const NOVA_COOLDOWN: f64 = 3.0;
+// what the Venom upgrade adds to every hit
+pub const VENOM: StatusEffect = StatusEffect { kind: StatusKind::Poison, duration: 4.0, magnitude: 0.5 };
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Experience {
+    pub level: u32,
+    pub xp: f64,
+}
+
+impl Experience {
+    pub fn to_next(&self) -> f64 {
+        10.0 + 8.0 * self.level as f64
+    }
+    // Adds `xp`, carrying any overflow into the next level. Returns how many levels were gained.
+    pub fn gain(&mut self, xp: f64) -> u32 {
+        self.xp += xp;
+
+        let mut gained = 0;
+        while self.xp >= self.to_next() {
+            self.xp -= self.to_next();
+            self.level += 1;
+            gained += 1;
+        }
+        gained
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Upgrade {
+    Damage,
+    Cooldown,
+    Range,
+    MaxHp,
+    // a second ability, fired by the same button on its own cooldown
+    Nova,
+    // hits poison
+    Venom,
+}
+
+impl Upgrade {
+    pub fn label(&self) -> &'static str {
+        use Upgrade::*;
+        match self {
+            Damage => "+25% damage",
+            Cooldown => "-15% cooldown",
+            Range => "+15 range",
+            MaxHp => "+5 max HP",
+            Nova => "New ability: Nova",
+            Venom => "Poisoned attacks",
+        }
+    }
+    pub fn apply(&self, player: &mut Object) {
+        use Upgrade::*;
+        match self {
+            Damage => {
+                player.action.scale(1.25);
+                if let Some((action, _)) = player.secondary.as_mut() {
+                    action.scale(1.25);
+                }
+            },
+            Cooldown => {
+                player.cooldown *= 0.85;
+                if let Some((_, cooldown)) = player.secondary.as_mut() {
+                    *cooldown *= 0.85;
+                }
+            },
+            Range => player.range_bonus += 15.0,
+            MaxHp => {
+                player.max_hp += 5.0;
+                player.hp += 5.0;
+            },
+            Nova => player.secondary = Some((Action::Nova(NOVA_MAGNITUDE), NOVA_COOLDOWN)),
+            Venom => player.on_hit.push(VENOM),
+        }
+    }
+    // Picks `CHOICES` different upgrades that still make sense for `player`
+    pub fn offer(player: &Object, rng: &mut impl Rng) -> Vec<Upgrade> {
+        use Upgrade::*;
+
+        let mut pool = vec![Damage, Cooldown, Range, MaxHp];
+        if player.secondary.is_none() {
+            pool.push(Nova);
+        }
+        if !player.on_hit.contains(&VENOM) {
+            pool.push(Venom);
+        }
+        pool.shuffle(rng);
+        pool.truncate(CHOICES);
+        pool
+    }
+}
@@ -0,0 +1,169 @@
+use rand::{
+    distributions::WeightedIndex,
+    prelude::Distribution,
+    Rng,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    archetype::Archetypes,
+    difficulty::Difficulty,
+    EnemyType,
+    Object,
+};
+
+// seconds of quiet before the first wave and between waves
+pub const FIRST_BREAK: f64 = 2.0;
+pub const WAVE_BREAK: f64 = 5.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WavePhase {
+    // counting down to the next wave
+    Break(f64),
+    // enemies still waiting to enter, and the time until the next one does
+    Spawning { queue: Vec<EnemyType>, timer: f64 },
+    // everything has spawned, waiting for the arena to be cleared
+    Fighting,
+    // the last wave has been cleared
+    Finished,
+}
+
+// What changed in a tick, worth telling the player about
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WaveNews {
+    Started(usize),
+    Cleared(usize),
+    // the last wave was cleared
+    AllCleared(usize),
+}
+
+impl WaveNews {
+    pub fn text(&self) -> String {
+        match self {
+            WaveNews::Started(wave) => format!("Wave {wave}"),
+            WaveNews::Cleared(wave) => format!("Wave {wave} cleared"),
+            WaveNews::AllCleared(waves) => format!("All {waves} waves cleared"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaveSpawner {
+    pub wave: usize,
+    // `None` keeps going forever
+    pub max_waves: Option<usize>,
+    pub phase: WavePhase,
+    pub difficulty: Difficulty,
+}
+
+impl WaveSpawner {
+    pub fn new(max_waves: Option<usize>, difficulty: Difficulty) -> Self {
+        WaveSpawner {
+            wave: 0,
+            max_waves,
+            phase: WavePhase::Break(FIRST_BREAK),
+            difficulty,
+        }
+    }
+    pub fn finished(&self) -> bool {
+        matches!(self.phase, WavePhase::Finished)
+    }
+    // Advances the wave state, pushing any newly spawned enemies onto `enemy_list`. Returns news
+    // whenever a wave starts or is cleared.
+    pub fn tick(
+        &mut self,
+        dt: f64,
+        archetypes: &Archetypes,
+        width: usize,
+        height: usize,
+        enemy_list: &mut Vec<Object>,
+        rng: &mut impl Rng,
+    ) -> Option<WaveNews> {
+        use WavePhase::*;
+        let profile = self.difficulty.profile();
+
+        match &mut self.phase {
+            Break(remaining) => {
+                *remaining -= dt;
+                if *remaining <= 0.0 {
+                    self.wave += 1;
+                    self.phase = Spawning {
+                        queue: wave_composition(self.wave, profile.mix_shift, rng),
+                        timer: 0.0,
+                    };
+                    return Some(WaveNews::Started(self.wave));
+                }
+            },
+            Spawning { queue, timer } => {
+                *timer -= dt;
+                if *timer <= 0.0 {
+                    if let Some(enemy_type) = queue.pop() {
+                        let (x, y) = spawn_point(width, height, rng);
+                        enemy_list.push(Object::enemy(enemy_type, archetypes, x, y, rng));
+                        *timer = spawn_interval(self.wave) / profile.spawn_rate;
+                    }
+                }
+                if queue.is_empty() {
+                    self.phase = Fighting;
+                }
+            },
+            Fighting => {
+                if enemy_list.is_empty() {
+                    if self.max_waves.is_some_and(|max| self.wave >= max) {
+                        self.phase = Finished;
+                        return Some(WaveNews::AllCleared(self.wave));
+                    }
+                    self.phase = Break(WAVE_BREAK);
+                    return Some(WaveNews::Cleared(self.wave));
+                }
+            },
+            Finished => {},
+        }
+
+        None
+    }
+}
+
+// Enemy count grows by three per wave, and the mix shifts from Weak towards Strong: Medium
+// enemies show up in numbers from wave 2 and Strong ones from wave 3. `mix_shift` draws the mix
+// from that many waves later without changing the count.
+pub fn wave_composition(wave: usize, mix_shift: i32, rng: &mut impl Rng) -> Vec<EnemyType> {
+    let count = 4 + wave * 3;
+    let mix = (wave as i32 + mix_shift).max(0) as usize;
+    let weights = [
+        8usize.saturating_sub(mix).max(1),
+        1 + mix,
+        mix.saturating_sub(2) * 2,
+    ];
+    let picker = WeightedIndex::new(weights).unwrap();
+
+    (0..count).map(|_| {
+        match picker.sample(rng) {
+            0 => EnemyType::Weak,
+            1 => EnemyType::Medium,
+            _ => EnemyType::Strong,
+        }
+    }).collect()
+}
+
+// seconds between individual spawns, shrinking as the waves go on
+pub fn spawn_interval(wave: usize) -> f64 {
+    (1.2 - 0.08 * wave as f64).max(0.25)
+}
+
+// how far in from the edge waves spawn, so the whole block is on screen
+pub const SPAWN_INSET: f64 = 8.0;
+
+// a random point on the edge of the arena, inset a little
+pub fn spawn_point(width: usize, height: usize, rng: &mut impl Rng) -> (f64, f64) {
+    let inset = SPAWN_INSET;
+    let (w, h) = (width as f64 - inset, height as f64 - inset);
+
+    match rng.gen_range(0..4) {
+        0 => (rng.gen_range(inset..w), inset),
+        1 => (rng.gen_range(inset..w), h),
+        2 => (inset, rng.gen_range(inset..h)),
+        _ => (w, rng.gen_range(inset..h)),
+    }
+}
@@ -0,0 +1,725 @@
+use image::RgbImage;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    anim_data::Alter,
+    boss::{
+        self,
+        BossEffect,
+    },
+    boundary::Boundary,
+    collider::Collider,
+    coop::{
+        Coop,
+        PARTNER_COLOR,
+        PARTNER_OFFSET,
+    },
+    damage::{
+        Damage,
+        DamageType,
+        Hit,
+    },
+    difficulty::Difficulty,
+    font,
+    input::InputState,
+    archetype::Archetypes,
+    level::{
+        Hazard,
+        Level,
+        Rect,
+        Spawn,
+        WinCondition,
+    },
+    pickup::{
+        self,
+        Pickup,
+        PickupKind,
+        PickupTable,
+        Stat,
+    },
+    projectile::Projectile,
+    rng::GameRng,
+    score::Score,
+    upgrade::{
+        Experience,
+        Upgrade,
+    },
+    spatial::{
+        SpatialHash,
+        CELL_SIZE,
+    },
+    state::{
+        GameState,
+        Menu,
+    },
+    wave::{
+        WaveNews,
+        WaveSpawner,
+    },
+    Object,
+    EnemyType,
+    ObjectName,
+    CONTACT_KNOCKBACK,
+    PROJECTILE_KNOCKBACK,
+};
+
+// half of the space kept clear between neighbouring enemies, in pixels
+pub const SEPARATION_GAP: f64 = 1.0;
+
+// pixels to the dot for the pause banner
+const PAUSED_SCALE: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Outcome {
+    Won,
+    Lost,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorldEvent {
+    Announce(String),
+    // the player levelled up and the game is paused until one of these is picked
+    LevelUp(Vec<String>),
+    Finished(Outcome),
+    // a hit landed on the player or an enemy, for the amount left after its defences
+    Damaged { player: bool, x: f64, y: f64, hit: Hit },
+    Killed(EnemyType),
+    WaveStarted(usize),
+    WaveCleared(usize),
+}
+
+// Everything the simulation needs for one level. The render thread ticks it and draws it, the
+// main thread pokes the player from input events.
+#[derive(Serialize, Deserialize)]
+pub struct World {
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    pub background: [u8; 3],
+    pub boundary: Boundary,
+    pub player: Object,
+    // the second player in co-op
+    pub partner: Option<Object>,
+    pub coop: Coop,
+    pub enemies: Vec<Object>,
+    pub projectiles: Vec<Projectile>,
+    pub pickups: Vec<Pickup>,
+    // indexes `enemies`, rebuilt whenever they move or the list changes
+    #[serde(skip, default = "empty_grid")]
+    pub grid: SpatialHash,
+    pub obstacles: Vec<Rect>,
+    pub exit: Option<Rect>,
+    pub hazards: Vec<Hazard>,
+    pub waves: Option<WaveSpawner>,
+    // scripted spawns that haven't entered yet, soonest last
+    pub spawns: Vec<Spawn>,
+    pub win: Option<WinCondition>,
+    pub time_limit: Option<f64>,
+    pub elapsed: f64,
+    pub bosses_killed: u32,
+    pub score: Score,
+    pub experience: Experience,
+    // level-ups that haven't had their upgrade picked yet
+    pub pending_levels: u32,
+    // while this is set the simulation is paused waiting for `choose_upgrade`
+    pub upgrade_choices: Option<Vec<Upgrade>>,
+    pub outcome: Option<Outcome>,
+    pub state: GameState,
+    #[serde(skip)]
+    pub menu: Menu,
+    // the stats and high scores shown on the game over screen
+    pub summary: String,
+    // directions each player is holding, set from input events
+    #[serde(skip)]
+    pub input: InputState,
+    #[serde(skip)]
+    pub partner_input: InputState,
+    pub difficulty: Difficulty,
+    // already scaled by `difficulty`
+    pub archetypes: Archetypes,
+    pub pickup_table: PickupTable,
+    pub rng: GameRng,
+    // the level as it was loaded, so a continued save can start over even if its file has gone
+    pub level: Level,
+}
+
+fn empty_grid() -> SpatialHash {
+    SpatialHash::new(CELL_SIZE)
+}
+
+impl World {
+    pub fn from_level(level: &Level, archetypes: &Archetypes, pickup_table: PickupTable, difficulty: Difficulty, coop: Coop) -> Self {
+        let archetypes = archetypes.with_difficulty(difficulty);
+        let (x, y) = level.player_start();
+        let player = Object::from_archetype(ObjectName::Player, archetypes.player(), x, y);
+        let partner = match coop {
+            Coop::Off => None,
+            Coop::Shared | Coop::Separate => {
+                let mut partner = Object::from_archetype(ObjectName::Player, archetypes.player(), x + PARTNER_OFFSET, y);
+                partner.color = PARTNER_COLOR;
+                Some(partner)
+            },
+        };
+
+        let mut spawns = level.spawns.clone();
+        spawns.sort_by(|a, b| b.time.total_cmp(&a.time));
+
+        World {
+            name: level.name.clone(),
+            width: level.width,
+            height: level.height,
+            background: level.background,
+            boundary: level.boundary.clone(),
+            player,
+            partner,
+            coop,
+            enemies: Vec::new(),
+            projectiles: Vec::new(),
+            pickups: Vec::new(),
+            grid: SpatialHash::new(CELL_SIZE),
+            obstacles: level.obstacles.clone(),
+            exit: level.exit,
+            hazards: level.hazards.clone(),
+            waves: level.waves.as_ref().map(|settings| WaveSpawner::new(settings.count, difficulty)),
+            spawns,
+            win: level.win.clone(),
+            time_limit: level.time_limit,
+            elapsed: 0.0,
+            bosses_killed: 0,
+            score: Score::default(),
+            experience: Experience::default(),
+            pending_levels: 0,
+            upgrade_choices: None,
+            outcome: None,
+            state: GameState::Title,
+            menu: Menu::default(),
+            summary: String::new(),
+            input: InputState::default(),
+            partner_input: InputState::default(),
+            difficulty,
+            archetypes,
+            pickup_table,
+            rng: GameRng::from_entropy(),
+            level: level.clone(),
+        }
+    }
+    // Applies the upgrade at `idx` among the current choices and resumes the game. Returns false
+    // if there was nothing to choose.
+    pub fn choose_upgrade(&mut self, idx: usize) -> bool {
+        match self.upgrade_choices.take() {
+            Some(choices) if idx < choices.len() => {
+                println!("Upgrade: {}", choices[idx].label());
+                // both players level up together, unless one has dropped out
+                let coop = self.coop;
+                for player in standing_players(&mut self.player, self.partner.as_mut(), coop) {
+                    choices[idx].apply(player);
+                }
+                true
+            },
+            choices => {
+                self.upgrade_choices = choices;
+                false
+            },
+        }
+    }
+    // Moves to `to` if that's a legal step from the current state, returning whether it did.
+    // Game over is reached by finishing the level rather than from here.
+    pub fn transition(&mut self, to: GameState) -> bool {
+        use GameState::*;
+        let legal = matches!(
+            (self.state, to),
+            (Title, Playing) | (Playing, Paused) | (Paused, Playing)
+        );
+        if legal {
+            self.state = to;
+            self.menu = Menu::default();
+        }
+        legal
+    }
+    // What the announcement line shows for the current screen: the menu, or the upgrade prompt
+    // if one is waiting
+    pub fn screen_text(&self) -> String {
+        let menu = self.menu.text(self.state, self.difficulty, self.coop);
+        match self.state {
+            GameState::Title => format!("{}\n\n{menu}", self.name),
+            GameState::Playing if self.upgrade_choices.is_some() => {
+                format!("Level {}! Pick an upgrade", self.experience.level)
+            },
+            GameState::Playing => String::new(),
+            GameState::Paused => format!("Paused\n\n{menu}"),
+            GameState::GameOver => format!("{}\n\n{menu}", self.summary),
+        }
+    }
+    // `partner` picks the second player's attack rather than the first's
+    pub fn player_attack(&mut self, partner: bool) {
+        if self.state != GameState::Playing || self.upgrade_choices.is_some() {
+            return;
+        }
+        let player = match (partner, self.partner.as_mut()) {
+            (false, _) => &mut self.player,
+            (true, Some(partner)) => partner,
+            (true, None) => return,
+        };
+        if player.hp > 0.0 {
+            player.use_ability(&mut self.enemies, &self.grid, &mut self.rng);
+        }
+    }
+    // Brings in a second player beside the first, with their own HP, for a networked player
+    // joining mid-level. Does nothing if there's already one.
+    pub fn add_partner(&mut self) {
+        if self.partner.is_some() {
+            return;
+        }
+        let mut partner = Object::from_archetype(ObjectName::Player, self.archetypes.player(), self.player.x + PARTNER_OFFSET, self.player.y);
+        partner.color = PARTNER_COLOR;
+        self.partner = Some(partner);
+        self.partner_input = InputState::default();
+        self.coop = Coop::Separate;
+    }
+    pub fn remove_partner(&mut self) {
+        self.partner = None;
+        self.partner_input = InputState::default();
+        self.coop = Coop::Off;
+    }
+    // The second player takes over the first player's place, for when the first one leaves
+    pub fn promote_partner(&mut self) {
+        if let Some(mut partner) = self.partner.take() {
+            partner.color = self.player.color;
+            self.player = partner;
+            self.input = std::mem::take(&mut self.partner_input);
+            self.coop = Coop::Off;
+        }
+    }
+    // Advances the simulation by `dt` seconds. Outside of `Playing` this does nothing, so the last
+    // frame stays on screen.
+    pub fn tick(&mut self, dt: f64) -> Vec<WorldEvent> {
+        let mut events = Vec::new();
+        if self.state != GameState::Playing || self.upgrade_choices.is_some() {
+            return events;
+        }
+
+        self.elapsed += dt;
+        self.score.tick(dt);
+        // what the shared pool held going in, to total up both players' changes at the end
+        let pool = self.player.hp;
+
+        if let Some(waves) = self.waves.as_mut() {
+            if let Some(news) = waves.tick(dt, &self.archetypes, self.width, self.height, &mut self.enemies, &mut self.rng) {
+                events.push(WorldEvent::Announce(news.text()));
+                events.push(match news {
+                    WaveNews::Started(wave) => WorldEvent::WaveStarted(wave),
+                    WaveNews::Cleared(wave) | WaveNews::AllCleared(wave) => WorldEvent::WaveCleared(wave),
+                });
+            }
+        }
+
+        while self.spawns.last().is_some_and(|spawn| spawn.time <= self.elapsed) {
+            let spawn = self.spawns.pop().unwrap();
+            for _ in 0..spawn.count {
+                self.enemies.push(Object::enemy(spawn.enemy.clone(), &self.archetypes, spawn.x, spawn.y, &mut self.rng));
+            }
+        }
+
+        self.grid.rebuild(&self.enemies);
+
+        self.input.tick(dt);
+        self.partner_input.tick(dt);
+        let controls = std::iter::once((&mut self.player, &self.input))
+            .chain(self.partner.as_mut().map(|partner| (partner, &self.partner_input)));
+        for (player, input) in controls {
+            // a player who dropped in separate co-op sits out the rest of the level
+            if !standing(player, self.coop) {
+                continue;
+            }
+            player.tick_buffs(dt);
+            player.tick_statuses(dt);
+            player.tick_recharge(dt);
+            player.drive(input.vector(), dt, &self.obstacles);
+            player.tick_knockback(dt, &self.obstacles);
+        }
+        for enemy in self.enemies.iter_mut() {
+            enemy.tick_statuses(dt);
+            enemy.tick_knockback(dt, &self.obstacles);
+        }
+
+        // A contact hit is worth one invulnerability window of touching, so contact damage comes
+        // out per second however fast the frames are
+        let coop = self.coop;
+        let players = standing_players(&mut self.player, self.partner.as_mut(), coop);
+        for player in players {
+            let (x, y, half) = (player.x, player.y, player.size / 2.0);
+            for idx in self.grid.query_aabb((x - half, y - half), (x + half, y + half)) {
+                let enemy = &self.enemies[idx];
+                if let Some(contact) = enemy.contact(player) {
+                    let damage = Damage::new(enemy.contact_dps * player.invulnerability.max(dt), DamageType::Kinetic);
+                    let (nx, ny) = contact.normal;
+                    player.take_hit(damage, (nx * CONTACT_KNOCKBACK, ny * CONTACT_KNOCKBACK), &mut self.rng);
+                }
+            }
+        }
+
+        let mut summons = Vec::new();
+        for enemy in self.enemies.iter_mut() {
+            // stunned enemies neither move nor run their patterns
+            if enemy.statuses.stunned() {
+                continue;
+            }
+            let target = nearest_player(&mut self.player, self.partner.as_mut(), self.coop, enemy.x, enemy.y);
+            for effect in boss::tick(enemy, dt, target, &self.obstacles) {
+                match effect {
+                    BossEffect::Fire(shots) => self.projectiles.extend(shots),
+                    BossEffect::Summon(enemy_type, count) => summons.push((enemy_type, count, enemy.x, enemy.y)),
+                    BossEffect::Announce(text) => events.push(WorldEvent::Announce(text)),
+                }
+            }
+            if !enemy.boss.as_ref().is_some_and(|boss| boss.busy()) {
+                enemy.steer_towards(target, dt, &self.obstacles);
+            }
+        }
+        for (enemy_type, count, x, y) in summons {
+            for i in 0..count {
+                let angle = std::f64::consts::TAU * i as f64 / count as f64;
+                let (sx, sy) = (x + angle.cos() * 30.0, y + angle.sin() * 30.0);
+                self.enemies.push(Object::enemy(enemy_type.clone(), &self.archetypes, sx, sy, &mut self.rng));
+            }
+        }
+
+        self.grid.rebuild(&self.enemies);
+        self.separate_enemies();
+
+        let (width, height) = (self.width, self.height);
+        for object in self.enemies.iter_mut().chain(std::iter::once(&mut self.player)).chain(self.partner.iter_mut()) {
+            self.boundary.confine(object, width, height);
+        }
+        // a player who's dropped out is past hurting
+        let players = standing_players(&mut self.player, self.partner.as_mut(), coop);
+        self.boundary.storm_damage(
+            self.enemies.iter_mut().chain(players),
+            self.elapsed,
+            dt,
+            width,
+            height,
+        );
+        let players = standing_players(&mut self.player, self.partner.as_mut(), coop);
+        for object in self.enemies.iter_mut().chain(players) {
+            for hazard in self.hazards.iter() {
+                if hazard.area.contains(object.x, object.y) {
+                    object.hp -= hazard.dps * dt;
+                }
+            }
+        }
+
+        let mut cached_delete = Vec::new();
+        for (i, enemy) in self.enemies.iter_mut().enumerate() {
+            for hit in enemy.hits.drain(..) {
+                events.push(WorldEvent::Damaged { player: false, x: enemy.x, y: enemy.y, hit });
+            }
+            let target = nearest_player(&mut self.player, self.partner.as_mut(), self.coop, enemy.x, enemy.y);
+            if let Some(projectile) = enemy.tick_action(dt, target, &mut self.rng) {
+                self.projectiles.push(projectile);
+            }
+
+            if enemy.hp <= 0.0 {
+                if let ObjectName::Enemy(enemy_type) = &enemy.name {
+                    events.push(WorldEvent::Killed(enemy_type.clone()));
+                    if let EnemyType::Boss = enemy_type {
+                        self.bosses_killed += 1;
+                    }
+                    let archetype = self.archetypes.enemy(enemy_type);
+                    self.score.kill(archetype.score);
+                    self.pending_levels += self.experience.gain(archetype.xp);
+                }
+                if let Some(pickup) = self.pickup_table.roll(&mut self.rng, enemy.x, enemy.y) {
+                    self.pickups.push(pickup);
+                }
+                cached_delete.push(i);
+            }
+        }
+
+        for idx in cached_delete.iter().rev() {
+            let _ = self.enemies.remove(*idx);
+        }
+        self.grid.rebuild(&self.enemies);
+
+        for projectile in self.projectiles.iter_mut() {
+            projectile.advance(dt);
+            if self.boundary == Boundary::Wrap {
+                projectile.x = projectile.x.rem_euclid(width as f64);
+                projectile.y = projectile.y.rem_euclid(height as f64);
+            }
+            for player in std::iter::once(&mut self.player).chain(self.partner.iter_mut()) {
+                if projectile.ttl <= 0.0 || !standing(player, self.coop) || !projectile.hits(player) {
+                    continue;
+                }
+                let speed = (projectile.vx.powf(2.0) + projectile.vy.powf(2.0)).sqrt().max(f64::EPSILON);
+                let impulse = (projectile.vx / speed * PROJECTILE_KNOCKBACK, projectile.vy / speed * PROJECTILE_KNOCKBACK);
+                if player.take_hit(projectile.damage, impulse, &mut self.rng) {
+                    player.afflict(&projectile.effects);
+                }
+                projectile.ttl = 0.0;
+            }
+        }
+        self.projectiles.retain(|projectile| !projectile.expired(width, height));
+
+        for pickup in self.pickups.iter_mut() {
+            pickup.ttl -= dt;
+            if pickup.ttl <= 0.0 {
+                continue;
+            }
+            let coop = self.coop;
+            let picker = std::iter::once(&mut self.player)
+                .chain(self.partner.iter_mut())
+                .find(|player| standing(player, coop) && pickup.touches(player));
+            if let Some(player) = picker {
+                pickup.ttl = 0.0;
+                if pickup.kind == PickupKind::Bomb {
+                    // the kills get scored, and roll their own drops, on the next tick
+                    for enemy in self.enemies.iter_mut() {
+                        enemy.hp = 0.0;
+                    }
+                    self.projectiles.clear();
+                } else {
+                    pickup::apply(pickup.kind, player);
+                }
+            }
+        }
+        self.pickups.retain(|pickup| pickup.ttl > 0.0);
+
+        if self.coop == Coop::Shared {
+            if let Some(partner) = self.partner.as_mut() {
+                let hp = (self.player.hp + partner.hp - pool).min(self.player.max_hp);
+                self.player.hp = hp;
+                partner.hp = hp;
+            }
+        }
+
+        for player in std::iter::once(&mut self.player).chain(self.partner.iter_mut()) {
+            for hit in player.hits.drain(..) {
+                events.push(WorldEvent::Damaged { player: true, x: player.x, y: player.y, hit });
+            }
+        }
+
+        if let Some(outcome) = self.check_outcome() {
+            self.outcome = Some(outcome);
+            self.state = GameState::GameOver;
+            self.menu = Menu::default();
+            events.push(WorldEvent::Finished(outcome));
+        } else if self.pending_levels > 0 {
+            self.pending_levels -= 1;
+            // offered for whoever's still up, so a downed first player isn't offered what the
+            // partner already has
+            let offered_to = std::iter::once(&self.player)
+                .chain(self.partner.iter())
+                .find(|player| standing(player, self.coop))
+                .unwrap_or(&self.player);
+            let choices = Upgrade::offer(offered_to, &mut self.rng);
+            events.push(WorldEvent::LevelUp(choices.iter().map(|upgrade| upgrade.label().to_string()).collect()));
+            self.upgrade_choices = Some(choices);
+        }
+
+        events
+    }
+    // Pushes overlapping enemies apart so a crowd chasing the same point spreads out around it
+    // instead of collapsing into one block. Each pair is split evenly, and the pushes go through
+    // `move_by` so nobody gets shoved into a wall. Expects `grid` to be current.
+    fn separate_enemies(&mut self) {
+        let mut pushes = vec![(0.0, 0.0); self.enemies.len()];
+
+        for (i, enemy) in self.enemies.iter().enumerate() {
+            let half = enemy.size / 2.0 + SEPARATION_GAP;
+            let collider = Collider::aabb(enemy.x, enemy.y, half, half);
+
+            for j in self.grid.query_aabb((enemy.x - half, enemy.y - half), (enemy.x + half, enemy.y + half)) {
+                // each pair only once
+                if j <= i {
+                    continue;
+                }
+                let other = &self.enemies[j];
+                let other_half = other.size / 2.0 + SEPARATION_GAP;
+                let other_collider = Collider::aabb(other.x, other.y, other_half, other_half);
+
+                if let Some(contact) = collider.contact(&other_collider) {
+                    let (nx, ny) = contact.normal;
+                    let push = contact.depth / 2.0;
+                    pushes[i].0 -= nx * push;
+                    pushes[i].1 -= ny * push;
+                    pushes[j].0 += nx * push;
+                    pushes[j].1 += ny * push;
+                }
+            }
+        }
+
+        for (enemy, (dx, dy)) in self.enemies.iter_mut().zip(pushes) {
+            if dx != 0.0 || dy != 0.0 {
+                enemy.move_by(dx, dy, &self.obstacles);
+            }
+        }
+    }
+    fn check_outcome(&self) -> Option<Outcome> {
+        // with a partner in separate co-op, the level goes on until both are down
+        if self.player.hp <= 0.0 && self.partner.as_ref().is_none_or(|partner| partner.hp <= 0.0) {
+            return Some(Outcome::Lost);
+        }
+        if self.time_limit.is_some_and(|limit| self.elapsed >= limit) {
+            return Some(Outcome::Lost);
+        }
+
+        let won = match &self.win {
+            Some(WinCondition::Survive(seconds)) => self.elapsed >= *seconds,
+            Some(WinCondition::KillAll) => {
+                self.spawns.is_empty()
+                    && self.waves.as_ref().is_none_or(|waves| waves.finished())
+                    && self.enemies.is_empty()
+            },
+            Some(WinCondition::ReachExit) => {
+                self.exit.is_some_and(|exit| {
+                    std::iter::once(&self.player)
+                        .chain(self.partner.iter())
+                        .any(|player| player.hp > 0.0 && exit.contains(player.x, player.y))
+                })
+            },
+            Some(WinCondition::DefeatBoss) => {
+                self.bosses_killed > 0 && !self.enemies.iter().any(|enemy| enemy.boss.is_some())
+            },
+            None => false,
+        };
+
+        if won {
+            Some(Outcome::Won)
+        } else {
+            None
+        }
+    }
+    pub fn hud(&self) -> String {
+        let hp = match (self.coop, self.partner.as_ref()) {
+            (Coop::Separate, Some(partner)) => format!(
+                "P1 {:.1}/{:.0}   P2 {:.1}/{:.0}",
+                self.player.hp.max(0.0),
+                self.player.max_hp,
+                partner.hp.max(0.0),
+                partner.max_hp,
+            ),
+            _ => format!("HP {:.1}/{:.0}", self.player.hp.max(0.0), self.player.max_hp),
+        };
+        let mut hud = format!(
+            "{hp}   Score {}   x{:.2}   Lv {} ({:.0}/{:.0} XP)",
+            self.score.total,
+            self.score.multiplier(),
+            self.experience.level,
+            self.experience.xp,
+            self.experience.to_next(),
+        );
+        for buff in self.player.buffs.iter() {
+            let name = match buff.stat {
+                Stat::Damage => "DMG",
+                Stat::Speed => "SPD",
+            };
+            hud.push_str(&format!("   {name} x{:.1} {:.0}s", buff.multiplier, buff.remaining.ceil()));
+        }
+        for status in self.player.statuses.active.iter() {
+            hud.push_str(&format!("   {} {:.0}s", status.effect.kind.label(), status.remaining.ceil()));
+        }
+        hud
+    }
+    fn draw_health_bar(&self, background: &mut RgbImage, enemy: &Object, top: f64) {
+        let (width, height) = (self.width, self.height);
+        let margin = width as f64 * 0.1;
+        let bar = Rect { x: margin, y: top, w: width as f64 - margin * 2.0, h: 8.0 };
+        let fill = Rect { w: bar.w * (enemy.hp / enemy.max_hp).clamp(0.0, 1.0), ..bar };
+
+        background.overlay(0,0,1.0,1.0, &bar.as_overlay(width, height, &[60,60,60]));
+        background.overlay(0,0,1.0,1.0, &fill.as_overlay(width, height, &[220,20,20]));
+
+        // a tick where each later phase kicks in
+        if let Some(state) = enemy.boss.as_ref() {
+            for phase in state.phases.iter().skip(1) {
+                let tick = Rect { x: bar.x + bar.w * phase.below, w: 2.0, ..bar };
+                background.overlay(0,0,1.0,1.0, &tick.as_overlay(width, height, &[255,255,255]));
+            }
+        }
+    }
+    pub fn render(&self) -> RgbImage {
+        let (width, height) = (self.width, self.height);
+        let mut background = RgbImage::from_pixel(width as u32, height as u32, self.background.into());
+
+        for obstacle in self.obstacles.iter() {
+            background.overlay(0,0,1.0,1.0, &obstacle.as_overlay(width, height, &[90,90,90]));
+        }
+        if let Some(exit) = self.exit {
+            background.overlay(0,0,1.0,1.0, &exit.as_overlay(width, height, &[60,200,60]));
+        }
+        for hazard in self.hazards.iter() {
+            background.overlay(0,0,1.0,1.0, &hazard.area.as_overlay(width, height, &Hazard::COLOR));
+        }
+
+        self.boundary.draw_storm(&mut background, self.elapsed);
+
+        let players = std::iter::once(&self.player)
+            .chain(self.partner.iter())
+            .filter(|player| standing(player, self.coop));
+        for object in players.chain(self.enemies.iter()) {
+            for (x, y) in self.boundary.draw_positions(object.x, object.y, object.size / 2.0, width, height) {
+                let placed = Object { x, y, ..object.clone() };
+                background.overlay(0,0,1.0,1.0, &placed.as_overlay(width, height));
+            }
+        }
+
+        for pickup in self.pickups.iter() {
+            background.overlay(0,0,1.0,1.0, &pickup.as_overlay(width, height));
+        }
+
+        for projectile in self.projectiles.iter() {
+            for (x, y) in self.boundary.draw_positions(projectile.x, projectile.y, projectile.size / 2.0, width, height) {
+                let placed = Projectile { x, y, ..projectile.clone() };
+                background.overlay(0,0,1.0,1.0, &placed.as_overlay(width, height));
+            }
+        }
+
+        // boss health bars stack down from the top edge
+        for (i, enemy) in self.enemies.iter().filter(|enemy| enemy.boss.is_some()).enumerate() {
+            self.draw_health_bar(&mut background, enemy, 8.0 + i as f64 * 14.0);
+        }
+
+        if self.state == GameState::Paused {
+            draw_paused(&mut background);
+        }
+
+        background
+    }
+}
+
+// Whether a player is still in the level. Only separate co-op takes a player out when their HP
+// runs out; otherwise that ends the level anyway.
+fn standing(player: &Object, coop: Coop) -> bool {
+    player.hp > 0.0 || coop != Coop::Separate
+}
+
+// The players still in the level, first player first
+fn standing_players<'a>(player: &'a mut Object, partner: Option<&'a mut Object>, coop: Coop) -> impl Iterator<Item = &'a mut Object> {
+    std::iter::once(player)
+        .chain(partner)
+        .filter(move |player| standing(player, coop))
+}
+
+// The player an enemy at (x, y) goes after: whichever standing one is closer
+fn nearest_player<'a>(player: &'a mut Object, partner: Option<&'a mut Object>, coop: Coop, x: f64, y: f64) -> &'a mut Object {
+    let distance = |object: &Object| (object.x - x).powf(2.0) + (object.y - y).powf(2.0);
+    match partner {
+        Some(partner) if standing(partner, coop)
+            && (!standing(player, coop) || distance(partner) < distance(player)) => partner,
+        _ => player,
+    }
+}
+
+// Dims the frame to half brightness and writes PAUSED across the middle
+fn draw_paused(image: &mut RgbImage) {
+    for pixel in image.pixels_mut() {
+        pixel.0 = pixel.0.map(|channel| channel / 2);
+    }
+
+    let (width, height) = image.dimensions();
+    let left = width.saturating_sub(font::text_width("PAUSED", PAUSED_SCALE)) / 2;
+    let top = height.saturating_sub(font::GLYPH_HEIGHT * PAUSED_SCALE) / 2;
+    font::draw_text(image, "PAUSED", left, top, PAUSED_SCALE, [255,255,255]);
+}